@@ -7,7 +7,12 @@ mod impl_traits;
 #[cfg(test)]
 mod tests;
 
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 #[cfg(feature = "cli")]
 use clap::{Subcommand, ValueEnum};
@@ -21,7 +26,9 @@ use crate::{
     error::OrganizeError,
 };
 
-type ActionClosure<'a, C> =
+pub(crate) use impl_::template_context;
+
+pub(crate) type ActionClosure<'a, C> =
     Box<dyn FnMut(&DirEntry<C>, bool) -> Result<ActionResultKind, OrganizeError> + 'a>;
 
 /// A preview for an action to be executed
@@ -38,6 +45,15 @@ pub enum ActionResultKind {
     },
     /// action has been successful
     Successful,
+    /// action has been applied to the items of a directory
+    PerItem {
+        /// items the action has been applied to successfully
+        successful: Vec<PathBuf>,
+        /// items the action failed for, with the reason
+        failed: Vec<(PathBuf, String)>,
+    },
+    /// action has been skipped
+    Skipped,
     /// conflict
     Conflicted(ConflictKind),
 }
@@ -242,6 +258,15 @@ impl Default for WriteModeKind {
     }
 }
 
+/// The files the `write` action wrote to during a run
+///
+/// Each file has its own lock, so concurrent writes to the same file
+/// don't interleave, while writes to other files go ahead. Clones share
+/// the written files, so a run clears a file only once with
+/// `clear_before_first_write`.
+#[derive(Debug, Clone, Default)]
+pub struct WrittenFiles(Arc<Mutex<HashMap<PathBuf, Arc<Mutex<bool>>>>>);
+
 // TODO: Shell support
 // adapted from: https://organize.readthedocs.io/en/latest/actions/
 //
@@ -301,6 +326,8 @@ pub enum ActionKind {
     /// Copy a file or directory to a new location
     ///
     /// If the specified path does not exist it will be created.
+    /// Directories are copied including their content, permissions and
+    /// modification times are preserved.
     ///
     /// # Example
     ///
@@ -327,7 +354,7 @@ pub enum ActionKind {
     ///      actions:
     ///        - mode: destructive
     ///          action: !copy
-    ///            dst: ~/Desktop/{{uppercase(extension)}}
+    ///            dst: ~/Desktop/{uppercase(metadata.extension)}/
     ///            on_conflict: overwrite
     ///      tags:
     ///        - !custom Test::Action::Copy
@@ -343,15 +370,18 @@ pub enum ActionKind {
         #[cfg_attr(feature = "cli", arg(long))]
         dst: PathBuf,
         /// What should happen in case dest already exists.
-        /// One of skip, overwrite, trash, rename_new and rename_existing.
+        /// One of skip, overwrite, overwrite_empty, rename_new,
+        /// rename_existing, biggest, smallest, keep_newer, keep_older
+        /// and move_to. `trash` skips the item, as the copy would
+        /// be trashed right away.
         ///
-        /// Defaults to rename_new.
+        /// Defaults to skip.
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "ConflictResolutionKind::default")]
         on_conflict: ConflictResolutionKind,
         /// A template for renaming the file / dir in case of a conflict.
         ///
-        /// Defaults to `{name}_{counter}{extension}`
+        /// Defaults to `{metadata.name}_{utility.counter}.{metadata.extension}`
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "Option::default")]
         rename_template: Option<Vec<String>>,
@@ -520,6 +550,10 @@ pub enum ActionKind {
     /// If you only want to rename the file and keep the folder,
     /// it is easier to use the rename action.
     ///
+    /// The destination can contain templates, e.g.
+    /// `~/Archive/{metadata.last_modified.year}/`. If the items are
+    /// on different filesystems, the item is copied and removed afterwards.
+    ///
     /// # Example
     ///
     /// Use a placeholder to move all .pdf files into a "PDF" folder
@@ -549,7 +583,7 @@ pub enum ActionKind {
     ///            msg: "Move {{entry}}?"
     ///        - mode: destructive
     ///          action: !move
-    ///            dst: ~/Desktop/{{uppercase(extension)}}/
+    ///            dst: ~/Desktop/{uppercase(metadata.extension)}/
     ///            on_conflict: overwrite
     ///      tags:
     ///        - !custom Test::Action::Move
@@ -561,19 +595,21 @@ pub enum ActionKind {
         /// The destination where the file / dir should be moved
         /// to. If `dst` ends with a slash, it is assumed
         /// to be a target directory and the file / dir will be
-        /// moved into `dst`and keep its name.
+        /// moved into `dst` and keep its name.
         #[cfg_attr(feature = "cli", arg(long))]
         dst: PathBuf,
         /// What should happen in case dest already exists.
-        /// One of skip, overwrite, trash, rename_new and rename_existing.
+        /// One of skip, overwrite, overwrite_empty, trash, rename_new,
+        /// rename_existing, biggest, smallest, keep_newer, keep_older
+        /// and move_to.
         ///
-        /// Defaults to rename_new.
+        /// Defaults to skip.
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "ConflictResolutionKind::default")]
         on_conflict: ConflictResolutionKind,
         /// A template for renaming the file / dir in case of a conflict.
         ///
-        /// Defaults to `{name}_{counter}{extension}`
+        /// Defaults to `{metadata.name}_{utility.counter}.{metadata.extension}`
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "Option::default")]
         rename_template: Option<Vec<String>>,
@@ -589,6 +625,10 @@ pub enum ActionKind {
     NoAction,
     /// Rename a file
     ///
+    /// The new name can contain templates, which are rendered for
+    /// each item, e.g. `{lowercase(metadata.name)}_{utility.counter}.{metadata.extension}`.
+    /// `{utility.counter}` counts the items renamed by the rule, starting at 1.
+    ///
     /// # Example
     ///
     /// Convert all .PDF file extensions to lowercase (.pdf)
@@ -611,7 +651,7 @@ pub enum ActionKind {
     ///      actions:
     ///        - mode: destructive
     ///          action: !rename
-    ///            name: "{{lowercase(entry.name)}}"
+    ///            name: "{metadata.name}.{lowercase(metadata.extension)}"
     ///            on_conflict: skip
     ///      tags:
     ///        - !custom Test::Action::Rename
@@ -620,19 +660,21 @@ pub enum ActionKind {
     /// ```
     #[serde(rename = "rename")]
     Rename {
-        /// The new name for the file / dir.
+        /// The new name for the file / dir, the item stays in its folder.
         #[cfg_attr(feature = "cli", arg(long))]
         name: String,
         /// What should happen in case dest already exists.
-        /// One of skip, overwrite, trash, rename_new and rename_existing.
+        /// One of skip, overwrite, overwrite_empty, trash, rename_new,
+        /// rename_existing, biggest, smallest, keep_newer, keep_older
+        /// and move_to.
         ///
-        /// Defaults to rename_new.
+        /// Defaults to skip.
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "ConflictResolutionKind::default")]
         on_conflict: ConflictResolutionKind,
         /// A template for renaming the file / dir in case of a conflict.
         ///
-        /// Defaults to `{name}_{counter}{extension}`
+        /// Defaults to `{metadata.name}_{utility.counter}.{metadata.extension}`
         #[cfg_attr(feature = "cli", arg(long))]
         #[serde(default = "Option::default")]
         rename_template: Option<Vec<String>>,
@@ -806,8 +848,9 @@ pub enum ActionKind {
         #[serde(default = "bool::default")]
         newline: bool,
         /// Clears the file before first appending / prepending text to it.
-        /// This happens only the first time write_file is run. If the rule
-        /// filters don't match anything the file is left as it is.
+        /// This happens only the first time the action writes to the file
+        /// during a run. If the rule filters don't match anything the file
+        /// is left as it is.
         ///
         /// Defaults to `false`.
         #[cfg_attr(feature = "cli", arg(long))]
@@ -1,16 +1,36 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use displaydoc::Display;
+use jwalk::WalkDir;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    actions::ActionResultKind,
+    error::{OrganizeResult, TemplateErrorKind},
+    filesystem::{is_same_item, move_to, move_to_trash, remove_recursively},
+    templating::{render_templates, TemplateContext},
+};
+
 #[cfg(feature = "cli")]
 use clap::ValueEnum;
 
+/// Maximum number of attempts to find a free name for an item
+const MAX_RENAME_ATTEMPTS: usize = 10_000;
+
+/// Conflict resolutions chosen for single items, overriding the
+/// ones configured for their actions
+pub type ConflictResolutions = Vec<(PathBuf, ConflictResolutionKind)>;
+
 /// possible conflicts
-#[derive(Debug, Clone, Deserialize, Serialize, Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Display)]
 pub enum ConflictKind {
     /// file already exists
     AlreadyExisting,
+    /// another item of this run is placed at the same destination
+    SameDestination,
 }
 
 /// Actions for conflict resolution
@@ -58,3 +78,258 @@ impl Default for ConflictResolutionKind {
         Self::Skip
     }
 }
+
+impl ConflictResolutionKind {
+    /// Resolves the conflict that arises when `src` should be moved or
+    /// copied to an already existing `dst`
+    ///
+    /// `rename_template`s are rendered for the item that gets renamed,
+    /// the first one resulting in a free name is used. Without a
+    /// template, the name of `dst` is suffixed with a counter,
+    /// e.g. `invoice_1.pdf`.
+    ///
+    /// # Errors
+    ///
+    /// If the items can't be compared or no free name can be found
+    pub(crate) fn resolve(
+        &self,
+        src: &Path,
+        dst: &Path,
+        rename_template: Option<&[String]>,
+    ) -> OrganizeResult<ResolvedConflictKind> {
+        self.resolve_with(src, dst, rename_template, &existing_item)
+    }
+
+    /// Resolves the conflict like [`ConflictResolutionKind::resolve`], but
+    /// asks `occupant` which item is (or will be) placed at a path
+    ///
+    /// This allows to resolve conflicts between items of the same run,
+    /// before any of them has been touched.
+    pub(crate) fn resolve_with(
+        &self,
+        src: &Path,
+        dst: &Path,
+        rename_template: Option<&[String]>,
+        occupant: &dyn Fn(&Path) -> Option<PathBuf>,
+    ) -> OrganizeResult<ResolvedConflictKind> {
+        let Some(existing) = occupant(dst) else {
+            return Ok(ResolvedConflictKind::Proceed(dst.to_path_buf()));
+        };
+
+        if src == dst {
+            return Ok(ResolvedConflictKind::Skip);
+        }
+
+        // e.g. changing the case of a name on a case-insensitive filesystem
+        if is_same_item(src, dst) {
+            return Ok(ResolvedConflictKind::Proceed(dst.to_path_buf()));
+        }
+
+        let resolved = match self {
+            ConflictResolutionKind::Skip => ResolvedConflictKind::Skip,
+            ConflictResolutionKind::Overwrite => ResolvedConflictKind::Replace(dst.to_path_buf()),
+            ConflictResolutionKind::OverwriteEmpty => {
+                if is_empty(&existing)? {
+                    ResolvedConflictKind::Replace(dst.to_path_buf())
+                } else {
+                    ResolvedConflictKind::Unresolved(ConflictKind::AlreadyExisting)
+                }
+            }
+            ConflictResolutionKind::Biggest => {
+                replace_if(dst, item_size(src)? > item_size(&existing)?)
+            }
+            ConflictResolutionKind::Smallest => {
+                replace_if(dst, item_size(src)? < item_size(&existing)?)
+            }
+            ConflictResolutionKind::KeepNewer => {
+                replace_if(dst, last_modified(src)? > last_modified(&existing)?)
+            }
+            ConflictResolutionKind::KeepOlder => {
+                replace_if(dst, last_modified(src)? < last_modified(&existing)?)
+            }
+            ConflictResolutionKind::RenameNew => {
+                ResolvedConflictKind::Proceed(find_free_name(dst, src, rename_template, occupant)?)
+            }
+            ConflictResolutionKind::RenameExisting => ResolvedConflictKind::RenameExisting {
+                existing: dst.to_path_buf(),
+                renamed: find_free_name(dst, &existing, rename_template, occupant)?,
+            },
+            ConflictResolutionKind::Trash => ResolvedConflictKind::TrashItem,
+            ConflictResolutionKind::MoveToFolder { path } => {
+                let target = path.join(dst.file_name().unwrap_or_default());
+                if occupant(&target).is_some() {
+                    ResolvedConflictKind::Proceed(find_free_name(
+                        &target,
+                        src,
+                        rename_template,
+                        occupant,
+                    )?)
+                } else {
+                    ResolvedConflictKind::Proceed(target)
+                }
+            }
+        };
+
+        Ok(resolved)
+    }
+}
+
+/// The resolution chosen for the item at `path`, or the `configured` one
+pub(crate) fn resolution_for<'a>(
+    resolutions: &'a [(PathBuf, ConflictResolutionKind)],
+    path: &Path,
+    configured: &'a ConflictResolutionKind,
+) -> &'a ConflictResolutionKind {
+    resolutions
+        .iter()
+        .find_map(|(item, resolution)| (item == path).then_some(resolution))
+        .unwrap_or(configured)
+}
+
+/// How the conflict for a single item has been resolved
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum ResolvedConflictKind {
+    /// proceed with destination {0:?}
+    Proceed(PathBuf),
+    /// replace the existing item {0:?}
+    Replace(PathBuf),
+    /// rename the existing item {existing:?} to {renamed:?}
+    RenameExisting { existing: PathBuf, renamed: PathBuf },
+    /// move the item to the trash
+    TrashItem,
+    /// skip the item
+    Skip,
+    /// unresolved: {0}
+    Unresolved(ConflictKind),
+}
+
+impl ResolvedConflictKind {
+    /// The destination the item ends up at, if any
+    pub fn destination(&self) -> Option<&Path> {
+        match self {
+            ResolvedConflictKind::Proceed(dst) | ResolvedConflictKind::Replace(dst) => Some(dst),
+            ResolvedConflictKind::RenameExisting { existing, .. } => Some(existing),
+            ResolvedConflictKind::TrashItem
+            | ResolvedConflictKind::Skip
+            | ResolvedConflictKind::Unresolved(_) => None,
+        }
+    }
+
+    /// Carries out the resolution and applies `operation` to `src`
+    /// and its (freed) destination afterwards
+    ///
+    /// # Errors
+    ///
+    /// If the resolution or the operation fails
+    pub(crate) fn apply_with<F>(&self, src: &Path, operation: F) -> OrganizeResult<ActionResultKind>
+    where
+        F: FnOnce(&Path, &Path) -> OrganizeResult<ActionResultKind>,
+    {
+        match self {
+            ResolvedConflictKind::Proceed(dst) => operation(src, dst),
+            ResolvedConflictKind::Replace(dst) => {
+                remove_recursively(dst)?;
+                operation(src, dst)
+            }
+            ResolvedConflictKind::RenameExisting { existing, renamed } => {
+                move_to(existing, renamed)?;
+                operation(src, existing)
+            }
+            ResolvedConflictKind::TrashItem => {
+                move_to_trash(src)?;
+                Ok(ActionResultKind::Successful)
+            }
+            ResolvedConflictKind::Skip => Ok(ActionResultKind::Skipped),
+            ResolvedConflictKind::Unresolved(kind) => {
+                Ok(ActionResultKind::Conflicted(kind.clone()))
+            }
+        }
+    }
+}
+
+fn replace_if(dst: &Path, condition: bool) -> ResolvedConflictKind {
+    if condition {
+        ResolvedConflictKind::Replace(dst.to_path_buf())
+    } else {
+        ResolvedConflictKind::Skip
+    }
+}
+
+/// also treats dangling symlinks as existing
+pub(crate) fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// the item placed at `path` on disk
+fn existing_item(path: &Path) -> Option<PathBuf> {
+    exists(path).then(|| path.to_path_buf())
+}
+
+fn is_empty(path: &Path) -> std::io::Result<bool> {
+    if path.is_dir() {
+        Ok(path.read_dir()?.next().is_none())
+    } else {
+        Ok(path.metadata()?.len() == 0)
+    }
+}
+
+/// size of a file or of all files within a directory
+fn item_size(path: &Path) -> std::io::Result<u64> {
+    if !path.is_dir() {
+        return Ok(path.metadata()?.len());
+    }
+
+    Ok(WalkDir::new(path)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum())
+}
+
+fn last_modified(path: &Path) -> std::io::Result<SystemTime> {
+    path.metadata()?.modified()
+}
+
+/// Finds a free name next to `dst` for `item`
+fn find_free_name(
+    dst: &Path,
+    item: &Path,
+    rename_template: Option<&[String]>,
+    occupant: &dyn Fn(&Path) -> Option<PathBuf>,
+) -> OrganizeResult<PathBuf> {
+    let parent = dst.parent().unwrap_or_else(|| Path::new(""));
+
+    for counter in 1..=MAX_RENAME_ATTEMPTS {
+        let names = match rename_template {
+            Some(templates) if !templates.is_empty() => {
+                let context = TemplateContext::default().with_counter(counter);
+                templates
+                    .iter()
+                    .map(|template| render_templates(template, item, &context))
+                    .collect::<OrganizeResult<Vec<_>>>()?
+            }
+            _ => vec![default_free_name(dst, counter)],
+        };
+
+        if let Some(candidate) = names
+            .into_iter()
+            .map(|name| parent.join(name))
+            .find(|candidate| occupant(candidate).is_none())
+        {
+            return Ok(candidate);
+        }
+    }
+
+    Err(TemplateErrorKind::NoFreeNameFound(dst.to_path_buf()).into())
+}
+
+/// `{metadata.name}_{utility.counter}.{metadata.extension}` for `dst`
+fn default_free_name(dst: &Path, counter: usize) -> String {
+    let stem = dst.file_stem().unwrap_or_default().to_string_lossy();
+    match dst.extension() {
+        Some(extension) => format!("{stem}_{counter}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{counter}"),
+    }
+}
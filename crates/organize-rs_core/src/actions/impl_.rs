@@ -1,59 +1,203 @@
-use std::path::Path;
+use std::{
+    fmt::Display,
+    fs::{read_to_string, write, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
 
 use console::style;
+use itertools::{Either, Itertools};
 use jwalk::ClientState;
+use once_cell::sync::Lazy;
 
 use crate::{
-    actions::{ActionClosure, ActionKind, ActionResultKind},
-    filesystem::{move_to_trash, remove_irrecoverably, symlink_to},
+    actions::{
+        conflicts::{resolution_for, ConflictResolutionKind, ResolvedConflictKind},
+        ActionClosure, ActionKind, ActionResultKind, WriteModeKind, WrittenFiles,
+    },
+    actors::location_walker::EntryTemplateValues,
+    counters::COUNTER_KEY,
+    error::{ActionErrorKind, OrganizeResult},
+    filesystem::{
+        copy_dir_to, copy_to, create_parent_dirs, expand_path, move_to, move_to_trash,
+        remove_irrecoverably, symlink_to,
+    },
+    templating::{render_templates, TemplateContext},
 };
 
 impl ActionKind {
     pub fn get_action<C: ClientState>(&self) -> ActionClosure<C> {
+        static NO_VALUES: Lazy<EntryTemplateValues> = Lazy::new(EntryTemplateValues::new);
+
+        self.get_action_with(&[], &NO_VALUES, &WrittenFiles::default())
+    }
+
+    /// Like [`ActionKind::get_action`], but the conflicts of the items in
+    /// `resolutions` are resolved with the given resolution instead of the
+    /// configured one, and templates can refer to the `template_values`
+    /// the filters extracted from the items
+    ///
+    /// The `write` action records the files it wrote to in `written_files`,
+    /// all actions of a run should share them.
+    pub fn get_action_with<'a, C: ClientState>(
+        &'a self,
+        resolutions: &'a [(PathBuf, ConflictResolutionKind)],
+        template_values: &'a EntryTemplateValues,
+        written_files: &WrittenFiles,
+    ) -> ActionClosure<'a, C> {
         match self {
             ActionKind::NoAction => self.action_no_action(),
             ActionKind::Trash => self.action_move_to_trash(),
             ActionKind::Delete => self.action_delete(),
             ActionKind::Symlink { dst } => self.action_symlink(dst),
             ActionKind::Copy {
-                dst: _,
-                on_conflict: _,
-                rename_template: _,
-                filesystem: _,
-            } => todo!("not implemented (yet)!"),
+                dst,
+                on_conflict,
+                rename_template,
+                filesystem,
+            } => self.action_copy(
+                dst,
+                on_conflict,
+                rename_template,
+                filesystem,
+                resolutions,
+                template_values,
+            ),
             ActionKind::Rename {
-                name: _,
-                on_conflict: _,
-                rename_template: _,
-            } => todo!("not implemented (yet)!"),
+                name,
+                on_conflict,
+                rename_template,
+            } => self.action_rename(
+                name,
+                on_conflict,
+                rename_template,
+                resolutions,
+                template_values,
+            ),
             ActionKind::Move {
-                dst: _,
-                on_conflict: _,
-                rename_template: _,
-                filesystem: _,
-            } => todo!("not implemented (yet)!"),
-            ActionKind::Confirm { msg: _, vars: _ } => todo!("not implemented (yet)!"),
-            ActionKind::Echo { msg: _ } => todo!("not implemented (yet)!"),
+                dst,
+                on_conflict,
+                rename_template,
+                filesystem,
+            } => self.action_move(
+                dst,
+                on_conflict,
+                rename_template,
+                filesystem,
+                resolutions,
+                template_values,
+            ),
+            ActionKind::Confirm { .. } => self.action_not_implemented("Confirm"),
+            ActionKind::Echo { .. } => self.action_not_implemented("Echo"),
             ActionKind::Write {
-                txt: _,
-                file: _,
-                mode: _,
-                newline: _,
-                clear_before_first_write: _,
-                filesystem: _,
-            } => todo!("not implemented (yet)!"),
-            ActionKind::Shell {
-                command: _,
-                run_in_simulation: _,
-                ignore_errors: _,
-                simulation_output: _,
-                simulation_returncode: _,
-            } => todo!(),
+                txt,
+                file,
+                mode,
+                newline,
+                clear_before_first_write,
+                filesystem,
+            } => self.action_write(
+                txt,
+                file,
+                mode,
+                *newline,
+                *clear_before_first_write,
+                filesystem,
+                template_values,
+                written_files.clone(),
+            ),
+            ActionKind::Shell { .. } => self.action_not_implemented("Shell"),
             #[cfg(target_os = "osx")]
-            ActionKind::MacOsTags { tags } => todo!(),
+            ActionKind::MacOsTags { .. } => self.action_not_implemented("MacOsTags"),
+        }
+    }
+
+    /// The path the action would place the item at `path` at, for
+    /// actions that move, copy or rename items
+    ///
+    /// The `context` should hold the number the rule's counter assigned to
+    /// the item, see [`CounterSettings::number_items`].
+    ///
+    /// [`CounterSettings::number_items`]: crate::counters::CounterSettings::number_items
+    ///
+    /// # Errors
+    ///
+    /// If the destination can't be rendered for the item
+    pub fn destination(
+        &self,
+        path: &Path,
+        context: &TemplateContext,
+    ) -> OrganizeResult<Option<PathBuf>> {
+        match self {
+            ActionKind::Move { dst, .. } | ActionKind::Copy { dst, .. } => {
+                render_destination(path, dst, context).map(Some)
+            }
+            ActionKind::Rename { name, .. } => render_new_name(path, name, context).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Expands `~` and environment variables in the destinations and the
+    /// file of the action, see [`expand_path`]
+    ///
+    /// # Errors
+    ///
+    /// If a path can't be expanded
+    pub fn expand_paths(&mut self) -> OrganizeResult<()> {
+        match self {
+            ActionKind::Move {
+                dst, on_conflict, ..
+            }
+            | ActionKind::Copy {
+                dst, on_conflict, ..
+            } => {
+                *dst = expand_path(dst)?;
+                if let ConflictResolutionKind::MoveToFolder { path } = on_conflict {
+                    *path = expand_path(path)?;
+                }
+            }
+            ActionKind::Rename {
+                on_conflict: ConflictResolutionKind::MoveToFolder { path },
+                ..
+            } => *path = expand_path(path)?,
+            ActionKind::Symlink { dst } => *dst = expand_path(dst)?,
+            ActionKind::Write { file, .. } => *file = expand_path(file)?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// The conflict resolution and the rename templates of the action,
+    /// if it can run into conflicts
+    pub fn conflict_resolution(&self) -> Option<(&ConflictResolutionKind, Option<&[String]>)> {
+        match self {
+            ActionKind::Move {
+                on_conflict,
+                rename_template,
+                ..
+            }
+            | ActionKind::Copy {
+                on_conflict,
+                rename_template,
+                ..
+            }
+            | ActionKind::Rename {
+                on_conflict,
+                rename_template,
+                ..
+            } => Some((on_conflict, rename_template.as_deref())),
+            _ => None,
         }
     }
 
+    fn action_not_implemented<C: ClientState>(&self, name: &'static str) -> ActionClosure<C> {
+        Box::new(move |_entry, _preview| {
+            Err(ActionErrorKind::NotImplemented(name.to_string()).into())
+        })
+    }
+
     fn action_no_action<C: ClientState>(&self) -> ActionClosure<C> {
         Box::new(|entry, _preview| {
             Ok(ActionResultKind::Preview {
@@ -132,4 +276,338 @@ impl ActionKind {
             }
         })
     }
+
+    fn action_move<'a, C: ClientState>(
+        &'a self,
+        dst: &'a Path,
+        on_conflict: &'a ConflictResolutionKind,
+        rename_template: &'a Option<Vec<String>>,
+        filesystem: &'a Option<String>,
+        resolutions: &'a [(PathBuf, ConflictResolutionKind)],
+        template_values: &'a EntryTemplateValues,
+    ) -> ActionClosure<'a, C> {
+        Box::new(move |entry, preview| {
+            ensure_local_filesystem(filesystem.as_ref())?;
+
+            let path = entry.path();
+            let context = template_context(template_values, &path);
+            let target = render_destination(&path, dst, &context)?;
+            let resolved = resolution_for(resolutions, &path, on_conflict).resolve(
+                &path,
+                &target,
+                rename_template.as_deref(),
+            )?;
+
+            if preview {
+                Ok(ActionResultKind::Preview {
+                    msg: format!(
+                        "{} {}: '{}' -> {}",
+                        style("(Preview)").green(),
+                        style("Move").blue(),
+                        path.display(),
+                        describe_resolution(&target, &resolved)
+                    ),
+                    path,
+                    action: self.to_owned(),
+                })
+            } else {
+                resolved.apply_with(&path, move_item)
+            }
+        })
+    }
+
+    fn action_copy<'a, C: ClientState>(
+        &'a self,
+        dst: &'a Path,
+        on_conflict: &'a ConflictResolutionKind,
+        rename_template: &'a Option<Vec<String>>,
+        filesystem: &'a Option<String>,
+        resolutions: &'a [(PathBuf, ConflictResolutionKind)],
+        template_values: &'a EntryTemplateValues,
+    ) -> ActionClosure<'a, C> {
+        Box::new(move |entry, preview| {
+            ensure_local_filesystem(filesystem.as_ref())?;
+
+            let path = entry.path();
+            let context = template_context(template_values, &path);
+            let target = render_destination(&path, dst, &context)?;
+            let resolved = resolution_for(resolutions, &path, on_conflict).resolve(
+                &path,
+                &target,
+                rename_template.as_deref(),
+            )?;
+
+            if preview {
+                Ok(ActionResultKind::Preview {
+                    msg: format!(
+                        "{} {}: '{}' -> {}",
+                        style("(Preview)").green(),
+                        style("Copy").blue(),
+                        path.display(),
+                        describe_resolution(&target, &resolved)
+                    ),
+                    path,
+                    action: self.to_owned(),
+                })
+            } else if matches!(resolved, ResolvedConflictKind::TrashItem) {
+                // the copy would be trashed right away
+                Ok(ActionResultKind::Skipped)
+            } else {
+                resolved.apply_with(&path, copy_item)
+            }
+        })
+    }
+
+    fn action_rename<'a, C: ClientState>(
+        &'a self,
+        name: &'a str,
+        on_conflict: &'a ConflictResolutionKind,
+        rename_template: &'a Option<Vec<String>>,
+        resolutions: &'a [(PathBuf, ConflictResolutionKind)],
+        template_values: &'a EntryTemplateValues,
+    ) -> ActionClosure<'a, C> {
+        let mut applied = 0;
+
+        Box::new(move |entry, preview| {
+            // a preview shows the number the item gets when it's applied next,
+            // so previewing an item before applying it doesn't skip a number
+            let counter = applied + 1;
+            if !preview {
+                applied = counter;
+            }
+
+            let path = entry.path();
+            let mut context = template_context(template_values, &path);
+            // items the rule's counter didn't number are numbered in the
+            // order the action is applied to them
+            let is_numbered = template_values
+                .get(&path)
+                .is_some_and(|values| values.contains_key(COUNTER_KEY));
+            if !is_numbered {
+                context = context.with_counter(counter);
+            }
+            let target = render_new_name(&path, name, &context)?;
+            let resolved = resolution_for(resolutions, &path, on_conflict).resolve(
+                &path,
+                &target,
+                rename_template.as_deref(),
+            )?;
+
+            if preview {
+                Ok(ActionResultKind::Preview {
+                    msg: format!(
+                        "{} {}: '{}' -> {}",
+                        style("(Preview)").green(),
+                        style("Rename").blue(),
+                        path.display(),
+                        describe_resolution(&target, &resolved)
+                    ),
+                    path,
+                    action: self.to_owned(),
+                })
+            } else {
+                resolved.apply_with(&path, move_item)
+            }
+        })
+    }
+
+    /// Writes the rendered `txt` into the rendered `file` for each item
+    ///
+    /// With `clear_before_first_write`, a file is cleared only the first
+    /// time it is written to during a run, see [`WrittenFiles`].
+    #[allow(clippy::too_many_arguments)]
+    fn action_write<'a, C: ClientState>(
+        &'a self,
+        txt: &'a str,
+        file: &'a Path,
+        mode: &'a WriteModeKind,
+        newline: bool,
+        clear_before_first_write: bool,
+        filesystem: &'a Option<PathBuf>,
+        template_values: &'a EntryTemplateValues,
+        written_files: WrittenFiles,
+    ) -> ActionClosure<'a, C> {
+        Box::new(move |entry, preview| {
+            ensure_local_filesystem(filesystem.as_ref().map(|filesystem| filesystem.display()))?;
+
+            let path = entry.path();
+            let context = template_context(template_values, &path);
+            let mut text = render_templates(txt, &path, &context)?;
+            if newline {
+                text.push('\n');
+            }
+            let target = PathBuf::from(render_templates(&file.to_string_lossy(), &path, &context)?);
+
+            if preview {
+                return Ok(ActionResultKind::Preview {
+                    msg: format!(
+                        "{} {}: '{}' -> '{}' ({})",
+                        style("(Preview)").green(),
+                        style("Write").blue(),
+                        text.escape_debug(),
+                        target.display(),
+                        mode
+                    ),
+                    path,
+                    action: self.to_owned(),
+                });
+            }
+
+            written_files
+                .write_text(&target, &text, mode, clear_before_first_write)
+                .map_err(|source| ActionErrorKind::WritingFileFailed {
+                    path: target.clone(),
+                    source,
+                })?;
+
+            Ok(ActionResultKind::Successful)
+        })
+    }
+}
+
+fn move_item(src: &Path, dst: &Path) -> OrganizeResult<ActionResultKind> {
+    move_to(src, dst)
+        .map_err(std::convert::Into::into)
+        .map(|_| ActionResultKind::Successful)
+}
+
+fn ensure_local_filesystem(filesystem: Option<impl Display>) -> OrganizeResult<()> {
+    match filesystem {
+        Some(filesystem) => Err(ActionErrorKind::NotImplemented(format!(
+            "filesystems other than the local one: {filesystem}"
+        ))
+        .into()),
+        None => Ok(()),
+    }
+}
+
+impl WrittenFiles {
+    /// The lock of `file`, it guards whether `file` has been written to
+    fn lock_of(&self, file: &Path) -> Arc<Mutex<bool>> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(file.to_path_buf())
+            .or_default()
+            .clone()
+    }
+
+    /// Writes `text` into `file` in the given `mode`, the content of the
+    /// file is discarded first if `clear_before_first_write` is `true` and
+    /// nothing has been written to it yet
+    fn write_text(
+        &self,
+        file: &Path,
+        text: &str,
+        mode: &WriteModeKind,
+        clear_before_first_write: bool,
+    ) -> std::io::Result<()> {
+        let lock = self.lock_of(file);
+        let mut written = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let clear = clear_before_first_write && !*written;
+
+        if let Some(parent) = file
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        match mode {
+            WriteModeKind::Overwrite => write(file, text)?,
+            WriteModeKind::Append if clear => write(file, text)?,
+            WriteModeKind::Append => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)?
+                .write_all(text.as_bytes())?,
+            WriteModeKind::Prepend => {
+                let existing = match read_to_string(file) {
+                    _ if clear => String::new(),
+                    Ok(existing) => existing,
+                    Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+                    Err(err) => return Err(err),
+                };
+                write(file, format!("{text}{existing}"))?;
+            }
+        }
+        *written = true;
+
+        Ok(())
+    }
+}
+
+/// Copies `src` to `dst`, for directories the result for each
+/// contained file is reported
+fn copy_item(src: &Path, dst: &Path) -> OrganizeResult<ActionResultKind> {
+    if !src.is_dir() {
+        copy_to(src, dst)?;
+        return Ok(ActionResultKind::Successful);
+    }
+
+    create_parent_dirs(dst)?;
+    let (successful, failed) =
+        copy_dir_to(src, dst)?
+            .into_iter()
+            .partition_map(|(path, result)| match result {
+                Ok(()) => Either::Left(path),
+                Err(err) => Either::Right((path, err.to_string())),
+            });
+
+    Ok(ActionResultKind::PerItem { successful, failed })
+}
+
+/// The context for rendering templates for the item at `path`
+pub(crate) fn template_context(
+    template_values: &EntryTemplateValues,
+    path: &Path,
+) -> TemplateContext {
+    TemplateContext::default().with_values(template_values.get(path).cloned().unwrap_or_default())
+}
+
+/// Renders the templated `name` for the item at `src` and returns
+/// the renamed path
+fn render_new_name(src: &Path, name: &str, context: &TemplateContext) -> OrganizeResult<PathBuf> {
+    let new_name = render_templates(name, src, context)?;
+
+    if new_name.is_empty() || new_name.contains(std::path::is_separator) {
+        return Err(ActionErrorKind::InvalidFileName(new_name).into());
+    }
+
+    Ok(src.with_file_name(new_name))
+}
+
+/// Renders the templates in `dst` for the item at `src`
+///
+/// If `dst` ends with a path separator, it is treated as a directory
+/// and the item keeps its name.
+fn render_destination(
+    src: &Path,
+    dst: &Path,
+    context: &TemplateContext,
+) -> OrganizeResult<PathBuf> {
+    let rendered = render_templates(&dst.to_string_lossy(), src, context)?;
+
+    if rendered.ends_with(std::path::is_separator) {
+        let file_name = src
+            .file_name()
+            .ok_or_else(|| ActionErrorKind::NoFileName(src.to_path_buf()))?;
+        Ok(PathBuf::from(rendered).join(file_name))
+    } else {
+        Ok(PathBuf::from(rendered))
+    }
+}
+
+fn describe_resolution(target: &Path, resolved: &ResolvedConflictKind) -> String {
+    match resolved {
+        ResolvedConflictKind::Proceed(dst) if dst == target => {
+            format!("'{}'", dst.display())
+        }
+        _ => format!(
+            "'{}' ({} {})",
+            target.display(),
+            style("conflict:").yellow(),
+            resolved
+        ),
+    }
 }
@@ -1 +1,485 @@
+//! unit tests for actions
 
+use std::{
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+use filetime::FileTime;
+use jwalk::{DirEntry, WalkDir};
+use pretty_assertions::assert_eq;
+use rstest::*;
+use tempfile::{tempdir, TempDir};
+
+use crate::{
+    actions::{
+        conflicts::ConflictResolutionKind, ActionKind, ActionResultKind, WriteModeKind,
+        WrittenFiles,
+    },
+    actors::location_walker::EntryTemplateValues,
+    templating::TemplateValues,
+};
+
+fn get_entry(path: impl AsRef<Path>) -> DirEntry<((), ())> {
+    WalkDir::new(path)
+        .into_iter()
+        .next()
+        .expect("path should exist")
+        .expect("entry should be readable")
+}
+
+/// creates `src/invoice.pdf` and, if given, an already existing `dst/invoice.pdf`
+fn setup_move(existing_content: Option<&str>) -> (TempDir, PathBuf, PathBuf) {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src").join("invoice.pdf");
+    let dst = dir.path().join("dst").join("invoice.pdf");
+
+    std::fs::create_dir_all(src.parent().unwrap()).unwrap();
+    write(&src, "new content").unwrap();
+
+    if let Some(content) = existing_content {
+        std::fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        write(&dst, content).unwrap();
+    }
+
+    (dir, src, dst)
+}
+
+fn move_action(dst: impl Into<PathBuf>, on_conflict: ConflictResolutionKind) -> ActionKind {
+    ActionKind::Move {
+        dst: dst.into(),
+        on_conflict,
+        rename_template: None,
+        filesystem: None,
+    }
+}
+
+#[test]
+fn test_action_move_into_directory_passes() {
+    let (dir, src, dst) = setup_move(None);
+    let action = move_action(
+        format!(
+            "{}{}",
+            dir.path().join("dst").display(),
+            std::path::MAIN_SEPARATOR
+        ),
+        ConflictResolutionKind::Skip,
+    );
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Successful));
+    assert!(!src.exists());
+    assert_eq!(read_to_string(dst).unwrap(), "new content");
+}
+
+#[test]
+fn test_action_move_to_templated_destination_passes() {
+    let (dir, src, _) = setup_move(None);
+    let action = move_action(
+        dir.path()
+            .join("archive")
+            .join("{uppercase(metadata.extension)}")
+            .join("{metadata.name}_moved.{metadata.extension}"),
+        ConflictResolutionKind::Skip,
+    );
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Successful));
+    assert!(dir
+        .path()
+        .join("archive")
+        .join("PDF")
+        .join("invoice_moved.pdf")
+        .exists());
+}
+
+#[test]
+fn test_action_move_preview_leaves_item_untouched_passes() {
+    let (dir, src, dst) = setup_move(None);
+    let action = move_action(
+        dir.path().join("dst").join("invoice.pdf"),
+        Default::default(),
+    );
+
+    let result = action.get_action()(&get_entry(&src), true).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Preview { .. }));
+    assert!(src.exists());
+    assert!(!dst.exists());
+}
+
+#[rstest]
+#[case(ConflictResolutionKind::Skip, "existing", true, None)]
+#[case(ConflictResolutionKind::Overwrite, "new content", false, None)]
+#[case(ConflictResolutionKind::OverwriteEmpty, "new content", false, None)]
+#[case(ConflictResolutionKind::RenameNew, "existing", false, Some(("invoice_1.pdf", "new content")))]
+#[case(ConflictResolutionKind::RenameExisting, "new content", false, Some(("invoice_1.pdf", "existing")))]
+#[case(ConflictResolutionKind::Biggest, "new content", false, None)]
+#[case(ConflictResolutionKind::Smallest, "existing", true, None)]
+fn test_action_move_with_conflict_passes(
+    #[case] on_conflict: ConflictResolutionKind,
+    #[case] dst_content: &str,
+    #[case] src_remains: bool,
+    #[case] renamed: Option<(&str, &str)>,
+) {
+    let existing = if matches!(on_conflict, ConflictResolutionKind::OverwriteEmpty) {
+        ""
+    } else {
+        "existing"
+    };
+    let (_dir, src, dst) = setup_move(Some(existing));
+    let action = move_action(dst.clone(), on_conflict);
+
+    _ = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert_eq!(read_to_string(&dst).unwrap(), dst_content);
+    assert_eq!(src.exists(), src_remains);
+    if let Some((name, content)) = renamed {
+        assert_eq!(
+            read_to_string(dst.parent().unwrap().join(name)).unwrap(),
+            content
+        );
+    }
+}
+
+#[test]
+fn test_action_move_overwrite_empty_with_content_conflicts_passes() {
+    let (_dir, src, dst) = setup_move(Some("existing"));
+    let action = move_action(dst.clone(), ConflictResolutionKind::OverwriteEmpty);
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Conflicted(_)));
+    assert!(src.exists());
+    assert_eq!(read_to_string(dst).unwrap(), "existing");
+}
+
+#[test]
+fn test_action_move_with_rename_template_passes() {
+    let (_dir, src, dst) = setup_move(Some("existing"));
+    let action = ActionKind::Move {
+        dst: dst.clone(),
+        on_conflict: ConflictResolutionKind::RenameNew,
+        rename_template: Some(vec![
+            "{metadata.name}-copy{utility.counter}.{metadata.extension}".to_string(),
+        ]),
+        filesystem: None,
+    };
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Successful));
+    assert_eq!(
+        read_to_string(dst.parent().unwrap().join("invoice-copy1.pdf")).unwrap(),
+        "new content"
+    );
+}
+
+#[test]
+fn test_action_move_to_folder_on_conflict_passes() {
+    let (dir, src, dst) = setup_move(Some("existing"));
+    let inspect = dir.path().join("inspect");
+    let action = move_action(
+        dst,
+        ConflictResolutionKind::MoveToFolder {
+            path: inspect.clone(),
+        },
+    );
+
+    _ = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert_eq!(
+        read_to_string(inspect.join("invoice.pdf")).unwrap(),
+        "new content"
+    );
+}
+
+fn copy_action(dst: impl Into<PathBuf>, on_conflict: ConflictResolutionKind) -> ActionKind {
+    ActionKind::Copy {
+        dst: dst.into(),
+        on_conflict,
+        rename_template: None,
+        filesystem: None,
+    }
+}
+
+#[test]
+fn test_action_copy_file_preserves_modification_time_passes() {
+    let (_dir, src, dst) = setup_move(None);
+    let mtime = FileTime::from_unix_time(1_600_000_000, 0);
+    filetime::set_file_mtime(&src, mtime).unwrap();
+    let action = copy_action(dst.clone(), ConflictResolutionKind::Skip);
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Successful));
+    assert!(src.exists());
+    assert_eq!(read_to_string(&dst).unwrap(), "new content");
+    assert_eq!(
+        FileTime::from_last_modification_time(&dst.metadata().unwrap()),
+        mtime
+    );
+}
+
+#[test]
+fn test_action_copy_directory_tree_reports_per_file_passes() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("documents");
+    std::fs::create_dir_all(src.join("nested")).unwrap();
+    write(src.join("a.txt"), "a").unwrap();
+    write(src.join("nested").join("b.txt"), "b").unwrap();
+    let backup = dir.path().join("backup").join("documents");
+    let action = copy_action(backup.clone(), ConflictResolutionKind::Skip);
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    let ActionResultKind::PerItem { successful, failed } = result else {
+        panic!("copying a directory should report per file results: {result:?}");
+    };
+    assert_eq!(successful.len(), 2);
+    assert!(failed.is_empty());
+    assert_eq!(
+        read_to_string(backup.join("nested").join("b.txt")).unwrap(),
+        "b"
+    );
+    assert!(src.join("a.txt").exists());
+}
+
+#[rstest]
+#[case(ConflictResolutionKind::RenameNew, Some("invoice_1.pdf"))]
+#[case(ConflictResolutionKind::Trash, None)]
+fn test_action_copy_with_conflict_passes(
+    #[case] on_conflict: ConflictResolutionKind,
+    #[case] copied_to: Option<&str>,
+) {
+    let (_dir, src, dst) = setup_move(Some("existing"));
+    let action = copy_action(dst.clone(), on_conflict);
+
+    _ = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(src.exists());
+    assert_eq!(read_to_string(&dst).unwrap(), "existing");
+    if let Some(name) = copied_to {
+        assert_eq!(
+            read_to_string(dst.parent().unwrap().join(name)).unwrap(),
+            "new content"
+        );
+    }
+}
+
+fn rename_action(name: &str, on_conflict: ConflictResolutionKind) -> ActionKind {
+    ActionKind::Rename {
+        name: name.to_string(),
+        on_conflict,
+        rename_template: None,
+    }
+}
+
+#[test]
+fn test_action_rename_with_counter_passes() {
+    let dir = tempdir().unwrap();
+    let scans = ["Scan A.PDF", "Scan B.PDF"].map(|name| dir.path().join(name));
+    scans.iter().for_each(|scan| write(scan, "scan").unwrap());
+    let action = rename_action(
+        "{lowercase(metadata.name)}_{utility.counter}.{lowercase(metadata.extension)}",
+        ConflictResolutionKind::Skip,
+    );
+    let mut apply = action.get_action();
+
+    for scan in &scans {
+        let result = apply(&get_entry(scan), false).unwrap();
+        assert!(matches!(result, ActionResultKind::Successful));
+    }
+
+    assert!(dir.path().join("scan a_1.pdf").exists());
+    assert!(dir.path().join("scan b_2.pdf").exists());
+    assert!(scans.iter().all(|scan| !scan.exists()));
+}
+
+#[test]
+fn test_action_rename_with_counter_after_preview_passes() {
+    let dir = tempdir().unwrap();
+    let scan = dir.path().join("scan.pdf");
+    write(&scan, "scan").unwrap();
+    let action = rename_action("scan_{utility.counter}.pdf", ConflictResolutionKind::Skip);
+    let mut apply = action.get_action();
+
+    let ActionResultKind::Preview { msg, .. } = apply(&get_entry(&scan), true).unwrap() else {
+        panic!("previewing should return a preview");
+    };
+    _ = apply(&get_entry(&scan), false).unwrap();
+
+    assert!(msg.contains("scan_1.pdf"), "{msg}");
+    assert!(dir.path().join("scan_1.pdf").exists());
+}
+
+#[test]
+fn test_action_rename_with_conflict_skips_passes() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("report.txt");
+    let existing = dir.path().join("final.txt");
+    write(&src, "new content").unwrap();
+    write(&existing, "existing").unwrap();
+    let action = rename_action("final.{metadata.extension}", ConflictResolutionKind::Skip);
+
+    let result = action.get_action()(&get_entry(&src), false).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Skipped));
+    assert!(src.exists());
+    assert_eq!(read_to_string(existing).unwrap(), "existing");
+}
+
+#[test]
+fn test_action_rename_with_path_separator_fails() {
+    let (_dir, src, _) = setup_move(None);
+    let action = rename_action(
+        &format!("nested{}{{metadata.name}}", std::path::MAIN_SEPARATOR),
+        ConflictResolutionKind::Skip,
+    );
+
+    let result = action.get_action()(&get_entry(&src), false);
+
+    assert!(result.is_err());
+    assert!(src.exists());
+}
+
+#[test]
+fn test_action_move_with_regex_template_values_passes() {
+    let (dir, src, _) = setup_move(None);
+    let template_values = EntryTemplateValues::from([(
+        src.clone(),
+        TemplateValues::from([("regex.customer".to_string(), "acme".to_string())]),
+    )]);
+    let action = move_action(
+        dir.path()
+            .join("{regex.customer}")
+            .join("{metadata.name}.{metadata.extension}"),
+        ConflictResolutionKind::Skip,
+    );
+
+    let result = action.get_action_with(&[], &template_values, &WrittenFiles::default())(
+        &get_entry(&src),
+        false,
+    )
+    .unwrap();
+
+    assert!(matches!(result, ActionResultKind::Successful));
+    assert!(dir.path().join("acme").join("invoice.pdf").exists());
+}
+
+fn write_action(file: impl Into<PathBuf>, mode: WriteModeKind, clear: bool) -> ActionKind {
+    ActionKind::Write {
+        txt: "{metadata.name}".to_string(),
+        file: file.into(),
+        mode,
+        newline: true,
+        clear_before_first_write: clear,
+        filesystem: None,
+    }
+}
+
+/// creates `a.txt` and `b.txt` and an already existing `inventory.txt`
+fn setup_write() -> (TempDir, Vec<DirEntry<((), ())>>, PathBuf) {
+    let dir = tempdir().unwrap();
+    let entries = ["a.txt", "b.txt"]
+        .iter()
+        .map(|name| {
+            let path = dir.path().join(name);
+            write(&path, "").unwrap();
+            get_entry(path)
+        })
+        .collect();
+    let inventory = dir.path().join("inventory.txt");
+    write(&inventory, "existing\n").unwrap();
+
+    (dir, entries, inventory)
+}
+
+#[rstest]
+#[case(WriteModeKind::Append, false, "existing\na\nb\n")]
+#[case(WriteModeKind::Append, true, "a\nb\n")]
+#[case(WriteModeKind::Prepend, false, "b\na\nexisting\n")]
+#[case(WriteModeKind::Prepend, true, "b\na\n")]
+#[case(WriteModeKind::Overwrite, false, "b\n")]
+fn test_action_write_with_mode_passes(
+    #[case] mode: WriteModeKind,
+    #[case] clear: bool,
+    #[case] expected: &str,
+) {
+    let (_dir, entries, inventory) = setup_write();
+    let action = write_action(&inventory, mode, clear);
+    let mut apply = action.get_action();
+
+    for entry in &entries {
+        let result = apply(entry, false).unwrap();
+        assert!(matches!(result, ActionResultKind::Successful));
+    }
+
+    assert_eq!(read_to_string(inventory).unwrap(), expected);
+}
+
+#[test]
+fn test_action_write_to_templated_file_passes() {
+    let (dir, entries, _) = setup_write();
+    let action = write_action(
+        dir.path().join("lists").join("{metadata.extension}.list"),
+        WriteModeKind::Append,
+        true,
+    );
+    let mut apply = action.get_action();
+
+    for entry in &entries {
+        _ = apply(entry, false).unwrap();
+    }
+
+    assert_eq!(
+        read_to_string(dir.path().join("lists").join("txt.list")).unwrap(),
+        "a\nb\n"
+    );
+}
+
+#[test]
+fn test_action_write_preview_leaves_file_untouched_passes() {
+    let (_dir, entries, inventory) = setup_write();
+    let action = write_action(&inventory, WriteModeKind::Overwrite, true);
+
+    let result = action.get_action()(&entries[0], true).unwrap();
+
+    assert!(matches!(result, ActionResultKind::Preview { .. }));
+    assert_eq!(read_to_string(inventory).unwrap(), "existing\n");
+}
+
+#[test]
+fn test_action_write_concurrently_keeps_lines_intact_passes() {
+    let (_dir, entries, inventory) = setup_write();
+    let action = ActionKind::Write {
+        txt: "{metadata.name}".repeat(1_000),
+        file: inventory.clone(),
+        mode: WriteModeKind::Append,
+        newline: true,
+        clear_before_first_write: false,
+        filesystem: None,
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            _ = scope.spawn(|| {
+                let mut apply = action.get_action();
+                for _ in 0..25 {
+                    for entry in &entries {
+                        _ = apply(entry, false).unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    let content = read_to_string(inventory).unwrap();
+    let lines = content.lines().skip(1).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 200);
+    assert!(lines
+        .iter()
+        .all(|line| *line == "a".repeat(1_000) || *line == "b".repeat(1_000)));
+}
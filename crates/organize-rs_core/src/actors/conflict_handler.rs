@@ -1 +1,245 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::{Path, PathBuf},
+};
 
+use crate::{
+    actions::{
+        conflicts::{exists, ConflictKind, ConflictResolutionKind, ResolvedConflictKind},
+        template_context, ActionKind,
+    },
+    actors::location_walker::DirEntryData,
+    filesystem::is_same_item,
+    rules::Rule,
+};
+
+/// A conflict detected for an item before any action has been applied
+#[derive(Debug, Clone)]
+pub struct DetectedConflict {
+    /// name of the rule the action belongs to
+    pub rule: String,
+    /// the action that runs into the conflict
+    pub action: ActionKind,
+    /// the item the action is applied to
+    pub path: PathBuf,
+    /// where the action would place the item
+    pub destination: PathBuf,
+    /// what the destination collides with
+    pub kind: ConflictKind,
+    /// how the configured [`ConflictResolutionKind`] resolved the conflict
+    pub resolution: ResolvedConflictKind,
+}
+
+impl DetectedConflict {
+    /// Returns `true` if the configured strategy couldn't resolve the conflict
+    pub fn is_unresolved(&self) -> bool {
+        matches!(self.resolution, ResolvedConflictKind::Unresolved(_))
+    }
+}
+
+/// Detects destination collisions of actions that move, copy or rename items
+///
+/// Collisions are detected with items that already exist at the destination
+/// as well as between items of the same run, that would be placed at the
+/// same destination. The actions are simulated in the same order they are
+/// applied in, so the detected resolutions match the applied ones.
+#[derive(Debug)]
+pub struct ConflictHandler<'a> {
+    entries: &'a [(Rule, DirEntryData)],
+    /// destinations claimed during this run and the item placed there
+    claimed: HashMap<PathBuf, PathBuf>,
+}
+
+impl<'a> ConflictHandler<'a> {
+    pub fn new(entries: &'a [(Rule, DirEntryData)]) -> Self {
+        Self {
+            entries,
+            claimed: HashMap::new(),
+        }
+    }
+
+    /// Simulates all actions and returns the detected conflicts
+    ///
+    /// Items whose destination can't be determined are left out, applying
+    /// the action to them reports the error.
+    pub fn detect_conflicts(mut self) -> Vec<DetectedConflict> {
+        let mut conflicts = vec![];
+
+        for (rule, entries) in self.entries {
+            for container in rule.actions().iter() {
+                let Some((strategy, rename_template)) = container.action.conflict_resolution()
+                else {
+                    continue;
+                };
+
+                let template_values = rule.counter().number_items(&container.action, entries);
+                for entry in entries.iter() {
+                    let path = entry.path();
+                    let context = template_context(&template_values, &path);
+                    let Ok(Some(destination)) = container.action.destination(&path, &context)
+                    else {
+                        continue;
+                    };
+
+                    if let Some(conflict) = self.simulate(
+                        rule,
+                        &container.action,
+                        strategy,
+                        rename_template,
+                        &path,
+                        destination,
+                    ) {
+                        conflicts.push(conflict);
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Resolves the destination for a single item and claims the paths
+    /// the item and a renamed existing item end up at
+    fn simulate(
+        &mut self,
+        rule: &Rule,
+        action: &ActionKind,
+        strategy: &ConflictResolutionKind,
+        rename_template: Option<&[String]>,
+        path: &Path,
+        destination: PathBuf,
+    ) -> Option<DetectedConflict> {
+        let kind = match self.claimed.entry(destination.clone()) {
+            Entry::Occupied(_) => ConflictKind::SameDestination,
+            Entry::Vacant(_) if exists(&destination) && !is_same_item(path, &destination) => {
+                ConflictKind::AlreadyExisting
+            }
+            Entry::Vacant(vacant) => {
+                vacant.insert(path.to_path_buf());
+                return None;
+            }
+        };
+
+        let occupant = |candidate: &Path| {
+            self.claimed
+                .get(candidate)
+                .cloned()
+                .or_else(|| exists(candidate).then(|| candidate.to_path_buf()))
+        };
+
+        // an unresolvable item is applied and reported as failed later on
+        let resolution = strategy
+            .resolve_with(path, &destination, rename_template, &occupant)
+            .ok()?;
+
+        match &resolution {
+            ResolvedConflictKind::Proceed(dst) | ResolvedConflictKind::Replace(dst) => {
+                self.claimed.insert(dst.clone(), path.to_path_buf());
+            }
+            ResolvedConflictKind::RenameExisting { existing, renamed } => {
+                let previous = occupant(existing).unwrap_or_else(|| existing.clone());
+                self.claimed.insert(renamed.clone(), previous);
+                self.claimed.insert(existing.clone(), path.to_path_buf());
+            }
+            ResolvedConflictKind::TrashItem
+            | ResolvedConflictKind::Skip
+            | ResolvedConflictKind::Unresolved(_) => (),
+        }
+
+        Some(DetectedConflict {
+            rule: rule.name().to_string(),
+            action: action.clone(),
+            path: path.to_path_buf(),
+            destination,
+            kind,
+            resolution,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use jwalk::WalkDir;
+    use tempfile::{tempdir, TempDir};
+
+    use crate::actions::{ActionApplicationKind, ActionContainer};
+
+    use super::*;
+
+    /// creates `a/report.txt` and `b/report.txt`, which are moved into `archive/`
+    fn setup(on_conflict: ConflictResolutionKind) -> (TempDir, Vec<(Rule, DirEntryData)>) {
+        let dir = tempdir().unwrap();
+        let entries = ["a", "b"]
+            .iter()
+            .map(|sub_dir| {
+                let path = dir.path().join(sub_dir).join("report.txt");
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                write(&path, *sub_dir).unwrap();
+                WalkDir::new(path).into_iter().next().unwrap().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let rule = Rule::builder()
+            .name("Archive reports")
+            .action(ActionContainer {
+                mode: ActionApplicationKind::Destructive,
+                action: ActionKind::Move {
+                    dst: PathBuf::from(format!(
+                        "{}{}",
+                        dir.path().join("archive").display(),
+                        std::path::MAIN_SEPARATOR
+                    )),
+                    on_conflict,
+                    rename_template: None,
+                    filesystem: None,
+                },
+            })
+            .build();
+
+        (dir, vec![(rule, DirEntryData::from(entries))])
+    }
+
+    #[test]
+    fn test_detect_conflicts_between_items_of_same_run_passes() {
+        let (dir, entries) = setup(ConflictResolutionKind::RenameNew);
+
+        let conflicts = ConflictHandler::new(&entries).detect_conflicts();
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::SameDestination);
+        assert_eq!(conflicts[0].path, dir.path().join("b").join("report.txt"));
+        assert_eq!(
+            conflicts[0].resolution,
+            ResolvedConflictKind::Proceed(dir.path().join("archive").join("report_1.txt"))
+        );
+    }
+
+    #[test]
+    fn test_detect_conflicts_with_existing_item_unresolved_passes() {
+        let (dir, entries) = setup(ConflictResolutionKind::OverwriteEmpty);
+        std::fs::create_dir_all(dir.path().join("archive")).unwrap();
+        write(dir.path().join("archive").join("report.txt"), "existing").unwrap();
+
+        let conflicts = ConflictHandler::new(&entries).detect_conflicts();
+
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts
+            .iter()
+            .all(|conflict| conflict.kind == ConflictKind::AlreadyExisting
+                && conflict.is_unresolved()));
+    }
+
+    #[test]
+    fn test_detect_conflicts_without_conflicts_passes() {
+        let (_dir, mut entries) = setup(ConflictResolutionKind::Skip);
+        let (rule, data) = entries.pop().unwrap();
+        let single = data.into_iter().take(1).collect::<Vec<_>>();
+        let entries = vec![(rule, DirEntryData::from(single))];
+
+        let conflicts = ConflictHandler::new(&entries).detect_conflicts();
+
+        assert!(conflicts.is_empty());
+    }
+}
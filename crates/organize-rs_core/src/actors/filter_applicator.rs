@@ -1,11 +1,20 @@
-use crate::{actors::location_walker::DirEntryData, filters::FilterGroupCollection};
-use itertools::{Either, Itertools};
+use crate::{
+    actors::location_walker::DirEntryData, filters::FilterGroupCollection,
+    templating::TemplateValues,
+};
+use itertools::Itertools;
 
 use crate::filters::{
     FilterApplicationKind, FilterFilterClosureSliceMut, FilterGroup, FilterGroupOperationKind,
-    FilterKind,
+    FilterKind, ValueFilterClosure,
 };
 
+/// A filter group together with the closures of its filters
+type PreparedFilterGroup<'a> = (
+    &'a FilterGroup<Vec<FilterKind>>,
+    Vec<ValueFilterClosure<'a, ((), ())>>,
+);
+
 #[derive(Debug, Default)]
 pub struct FilterApplicator {
     filters: FilterGroupCollection,
@@ -16,29 +25,23 @@ impl FilterApplicator {
         FilterApplicator { filters }
     }
 
+    /// Returns the entries the filter groups select, together with the
+    /// template values the filters extracted from them
+    ///
+    /// Whether a group matches an entry depends on its `match` mode, `all`,
+    /// `any` or `none` of its filters need to match. The groups are then
+    /// evaluated in this order:
+    ///
+    /// 1. Entries matched by any `exclude` group are removed
+    /// 2. Of the remaining entries, the ones matched by any `include` group
+    ///    are kept. Without `include` groups, all remaining entries are kept.
     pub fn get_applicable_items(self, entries: DirEntryData) -> DirEntryData {
-        // extract ignore filters
-        let (ignore_filters, other_filters): (Vec<_>, Vec<_>) =
-            self.filters
-                .iter()
-                .partition_map(|filter| match filter.mode() {
-                    FilterApplicationKind::None => Either::Left(filter),
-                    _ => Either::Right(filter),
-                });
-
-        // split off any / all filters
-        let (any_filters, all_filters): (Vec<_>, Vec<_>) =
-            other_filters
-                .into_iter()
-                .partition_map(|filter| match filter.mode() {
-                    FilterApplicationKind::Any => Either::Left(filter),
-                    FilterApplicationKind::All => Either::Right(filter),
-                    _ => unreachable!(
-                        "There should be no items left in `FilterModeGroupKind::None`!"
-                    ),
-                });
-
-        Self::get_filtered_entries(entries, ignore_filters, any_filters, all_filters)
+        let (exclude_groups, include_groups): (Vec<_>, Vec<_>) = self
+            .filters
+            .iter()
+            .partition(|filter_group| filter_group.apply() == FilterGroupOperationKind::Exclude);
+
+        Self::get_filtered_entries(entries, exclude_groups, include_groups)
     }
 
     pub fn apply_filters(
@@ -57,78 +60,306 @@ impl FilterApplicator {
             .collect_vec()
     }
 
-    fn filter_group_applies(
-        filter_group: &FilterGroup<Vec<FilterKind>>,
+    /// Creates the filter closures of the groups, so they are set up only
+    /// once for all entries
+    ///
+    /// Filters that need to know all entries, e.g. to find duplicates,
+    /// examine `entries` here.
+    fn prepare_filter_groups<'a>(
+        filter_groups: Vec<&'a FilterGroup<Vec<FilterKind>>>,
+        entries: &DirEntryData,
+    ) -> Vec<PreparedFilterGroup<'a>> {
+        filter_groups
+            .into_iter()
+            .map(|filter_group| {
+                let filters = filter_group
+                    .filters()
+                    .iter()
+                    .map(|filter| filter.get_filter_for_entries(entries))
+                    .collect_vec();
+                (filter_group, filters)
+            })
+            .collect_vec()
+    }
+
+    /// Returns whether the group matches `entry` according to its mode and
+    /// adds the template values of the matching filters to `values`
+    ///
+    /// Filters are only run until the result of the group is decided. Only
+    /// if the group matches and `values` are requested, the remaining filters
+    /// of an `any` group are run for their template values.
+    fn filter_group_matches(
+        (filter_group, filters): &mut PreparedFilterGroup<'_>,
         entry: &jwalk::DirEntry<((), ())>,
+        values: Option<&mut TemplateValues>,
     ) -> bool {
-        let (matched, not_matched): (Vec<bool>, Vec<bool>) = filter_group
-            .filters()
-            .iter()
-            .map(|single_filter| single_filter.get_filter()(entry))
-            .partition(|f| *f);
+        let mut results = filters.iter_mut().map(|filter| filter(entry));
 
-        match (filter_group.apply(), filter_group.mode()) {
-            (FilterGroupOperationKind::Exclude, FilterApplicationKind::All)
-            | (FilterGroupOperationKind::Include, FilterApplicationKind::All) => {
-                not_matched.is_empty()
-            }
-            (FilterGroupOperationKind::Exclude, FilterApplicationKind::Any)
-            | (FilterGroupOperationKind::Include, FilterApplicationKind::Any) => {
-                !matched.is_empty()
+        match filter_group.mode() {
+            FilterApplicationKind::All => {
+                let mut group_values = TemplateValues::new();
+                for result in results {
+                    let Some(filter_values) = result else {
+                        return false;
+                    };
+                    group_values.extend(filter_values);
+                }
+                if let Some(values) = values {
+                    values.extend(group_values);
+                }
+                true
             }
-            (FilterGroupOperationKind::Exclude, FilterApplicationKind::None)
-            | (FilterGroupOperationKind::Include, FilterApplicationKind::None) => {
-                matched.is_empty()
+            FilterApplicationKind::Any => {
+                let Some(first_values) = results.find_map(|result| result) else {
+                    return false;
+                };
+                if let Some(values) = values {
+                    values.extend(first_values);
+                    values.extend(results.flatten().flatten());
+                }
+                true
             }
+            FilterApplicationKind::None => results.all(|result| result.is_none()),
         }
     }
 
     fn get_filtered_entries(
         entries: DirEntryData,
-        ignore_filters: Vec<&FilterGroup<Vec<FilterKind>>>,
-        any_filters: Vec<&FilterGroup<Vec<FilterKind>>>,
-        all_filters: Vec<&FilterGroup<Vec<FilterKind>>>,
+        exclude_groups: Vec<&FilterGroup<Vec<FilterKind>>>,
+        include_groups: Vec<&FilterGroup<Vec<FilterKind>>>,
     ) -> DirEntryData {
-        let filtered_entries = entries
+        let mut exclude_groups = Self::prepare_filter_groups(exclude_groups, &entries);
+        let mut include_groups = Self::prepare_filter_groups(include_groups, &entries);
+
+        let mut filtered_entries = DirEntryData::default();
+
+        for entry in entries {
+            let excluded = exclude_groups
+                .iter_mut()
+                .any(|filter_group| Self::filter_group_matches(filter_group, &entry, None));
+            if excluded {
+                continue;
+            }
+
+            // all include groups are evaluated, so every matching group
+            // provides its template values
+            let mut values = TemplateValues::new();
+            let matching_groups = include_groups
+                .iter_mut()
+                .map(|filter_group| {
+                    Self::filter_group_matches(filter_group, &entry, Some(&mut values))
+                })
+                .filter(|matches| *matches)
+                .count();
+
+            if include_groups.is_empty() || matching_groups > 0 {
+                filtered_entries.push(entry, values);
+            }
+        }
+
+        filtered_entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs::write, path::PathBuf};
+
+    use jwalk::WalkDir;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    use crate::filters::DuplicateKind;
+
+    use super::*;
+
+    /// the names of the files in the `by_name` fixture the groups select
+    fn select_by_name(groups: Vec<FilterGroup<Vec<FilterKind>>>) -> Vec<String> {
+        let dir: PathBuf = ["tests", "fixtures", "filters", "by_name"].iter().collect();
+        let entries = WalkDir::new(dir)
+            .sort(true)
             .into_iter()
-            .flat_map(|entry| {
-                if !ignore_filters.is_empty() {
-                    ignore_filters
-                        .iter()
-                        // .inspect(|f| println!("Applying ignore filter: {f}"))
-                        .map(|filter| Self::filter_group_applies(filter, &entry))
-                        .all(|f| matches!(f, false))
-                        .then_some(entry)
-                } else {
-                    Some(entry)
-                }
-            })
-            .flat_map(|entry| {
-                if !all_filters.is_empty() {
-                    all_filters
-                        .iter()
-                        // .inspect(|f| println!("Applying filter: {f}"))
-                        .map(|filter| Self::filter_group_applies(filter, &entry))
-                        .all(|f| matches!(f, true))
-                        .then_some(entry)
-                } else {
-                    Some(entry)
-                }
-            })
-            .flat_map(|entry| {
-                if !any_filters.is_empty() {
-                    any_filters
-                        .iter()
-                        // .inspect(|f| println!("Applying filter: {f}"))
-                        .map(|filter| Self::filter_group_applies(filter, &entry))
-                        .any(|f| matches!(f, true))
-                        .then_some(entry)
-                } else {
-                    Some(entry)
-                }
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .collect_vec();
+
+        FilterApplicator::new(FilterGroupCollection::from_vec(groups))
+            .get_applicable_items(DirEntryData::from(entries))
+            .iter()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn group(
+        operation: FilterGroupOperationKind,
+        mode: FilterApplicationKind,
+        filters: Vec<FilterKind>,
+    ) -> FilterGroup<Vec<FilterKind>> {
+        FilterGroup::new(operation, mode, filters)
+    }
+
+    fn extension(ext: &str) -> FilterKind {
+        FilterKind::Extension {
+            exts: vec![ext.to_string()],
+        }
+    }
+
+    fn regex(expr: &str) -> FilterKind {
+        FilterKind::Regex {
+            expr: expr.to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case::include(
+        vec![group(FilterGroupOperationKind::Include, FilterApplicationKind::All, vec![extension("txt")])],
+        vec!["123test1.txt", "456test2.txt", "TEST123.txt", "uTEST.txt"],
+    )]
+    #[case::include_none(
+        vec![group(FilterGroupOperationKind::Include, FilterApplicationKind::None, vec![extension("txt")])],
+        vec!["789TaSt.jpg"],
+    )]
+    #[case::exclude_only(
+        vec![group(FilterGroupOperationKind::Exclude, FilterApplicationKind::Any, vec![extension("txt")])],
+        vec!["789TaSt.jpg"],
+    )]
+    #[case::include_and_exclude(
+        vec![
+            group(FilterGroupOperationKind::Include, FilterApplicationKind::All, vec![extension("txt")]),
+            group(FilterGroupOperationKind::Exclude, FilterApplicationKind::Any, vec![regex(r"^\d")]),
+        ],
+        vec!["TEST123.txt", "uTEST.txt"],
+    )]
+    #[case::exclude_all(
+        vec![
+            group(FilterGroupOperationKind::Exclude, FilterApplicationKind::All, vec![extension("txt"), regex("TEST")]),
+        ],
+        vec!["123test1.txt", "456test2.txt", "789TaSt.jpg"],
+    )]
+    #[case::includes_are_combined(
+        vec![
+            group(FilterGroupOperationKind::Include, FilterApplicationKind::All, vec![extension("jpg")]),
+            group(FilterGroupOperationKind::Include, FilterApplicationKind::Any, vec![regex("^TEST")]),
+        ],
+        vec!["789TaSt.jpg", "TEST123.txt"],
+    )]
+    #[case::exclude_wins(
+        vec![
+            group(FilterGroupOperationKind::Include, FilterApplicationKind::All, vec![extension("txt")]),
+            group(FilterGroupOperationKind::Exclude, FilterApplicationKind::All, vec![extension("txt")]),
+        ],
+        vec![],
+    )]
+    fn test_get_applicable_items_with_groups_passes(
+        #[case] groups: Vec<FilterGroup<Vec<FilterKind>>>,
+        #[case] expected: Vec<&str>,
+    ) {
+        assert_eq!(select_by_name(groups), expected);
+    }
+
+    #[rstest]
+    #[case::all_stops_at_first_miss(FilterApplicationKind::All, &[false, true], false, false, 1)]
+    #[case::any_stops_at_first_hit(FilterApplicationKind::Any, &[true, true], false, true, 1)]
+    #[case::any_runs_all_for_values(FilterApplicationKind::Any, &[true, true], true, true, 2)]
+    #[case::any_runs_until_hit(FilterApplicationKind::Any, &[false, true, true], false, true, 2)]
+    #[case::none_stops_at_first_hit(FilterApplicationKind::None, &[true, false], false, false, 1)]
+    #[case::none_runs_all_misses(FilterApplicationKind::None, &[false, false], false, true, 2)]
+    fn test_filter_group_matches_short_circuits_passes(
+        #[case] mode: FilterApplicationKind,
+        #[case] hits: &[bool],
+        #[case] with_values: bool,
+        #[case] expected: bool,
+        #[case] expected_calls: usize,
+    ) {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("a.txt"), "").unwrap();
+        let entry = WalkDir::new(dir.path().join("a.txt"))
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap();
+        let calls = Cell::new(0);
+        let filter_group = group(FilterGroupOperationKind::Include, mode, vec![]);
+        let filters = hits
+            .iter()
+            .enumerate()
+            .map(|(index, hit)| -> ValueFilterClosure<'_, ((), ())> {
+                let calls = &calls;
+                Box::new(move |_| {
+                    calls.set(calls.get() + 1);
+                    hit.then(|| TemplateValues::from([(format!("filter.{index}"), String::new())]))
+                })
             })
             .collect_vec();
+        let mut values = TemplateValues::new();
+
+        let matches = FilterApplicator::filter_group_matches(
+            &mut (&filter_group, filters),
+            &entry,
+            with_values.then_some(&mut values),
+        );
+
+        assert_eq!(matches, expected);
+        assert_eq!(calls.get(), expected_calls);
+        if with_values {
+            assert_eq!(values.len(), expected_calls);
+        }
+    }
+
+    #[rstest]
+    #[case("include", FilterGroupOperationKind::Include)]
+    #[case("exclude", FilterGroupOperationKind::Exclude)]
+    fn test_deserialize_filter_group_operation_passes(
+        #[case] results: &str,
+        #[case] expected: FilterGroupOperationKind,
+    ) {
+        let group: FilterGroup<Vec<FilterKind>> = serde_yaml::from_str(&format!(
+            "results: {results}\nmatch: all\nfilters:\n  - !empty"
+        ))
+        .unwrap();
+
+        assert_eq!(group.apply(), expected);
+    }
+
+    #[test]
+    fn test_get_applicable_items_with_duplicates_and_regex_passes() {
+        let dir = tempdir().unwrap();
+        ["invoice_1.pdf", "invoice_2.pdf", "report.pdf"]
+            .iter()
+            .for_each(|name| write(dir.path().join(name), "same content").unwrap());
+        let entries = WalkDir::new(dir.path())
+            .sort(true)
+            .into_iter()
+            .flatten()
+            .collect_vec();
+
+        let filters = FilterGroupCollection::from_vec(vec![FilterGroup::new(
+            FilterGroupOperationKind::Include,
+            FilterApplicationKind::All,
+            vec![
+                FilterKind::Duplicate {
+                    detect_original_by: DuplicateKind::FirstSeen,
+                    reverse: false,
+                },
+                FilterKind::Regex {
+                    expr: r"_(?P<number>\d+)\.pdf$".to_string(),
+                },
+            ],
+        )]);
+
+        let items =
+            FilterApplicator::new(filters).get_applicable_items(DirEntryData::from(entries));
 
-        DirEntryData::from(filtered_entries)
+        let duplicate = dir.path().join("invoice_2.pdf");
+        assert_eq!(
+            items.iter().map(jwalk::DirEntry::path).collect_vec(),
+            vec![duplicate.clone()]
+        );
+        let values = &items.template_values()[&duplicate];
+        assert_eq!(values["regex.number"], "2");
+        assert_eq!(
+            values["duplicate.original"],
+            dir.path().join("invoice_1.pdf").to_string_lossy()
+        );
     }
 }
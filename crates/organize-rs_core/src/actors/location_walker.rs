@@ -1,18 +1,33 @@
 use itertools::Itertools;
 use jwalk::{ClientState, DirEntry};
-use std::{fmt::Display, fs::FileType, path::Path, slice::Iter, vec::IntoIter};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::FileType,
+    path::{Path, PathBuf},
+    slice::Iter,
+    vec::IntoIter,
+};
 
 use crate::{
     error::{OrganizeResult, WalkerErrorKind},
     locations::{LocationCollection, LocationKind, MaxDepth, TargetKind},
+    templating::TemplateValues,
 };
 
 pub struct IterCarry<'it, C: ClientState> {
     pub iter: &'it mut dyn Iterator<Item = jwalk::DirEntry<C>>,
 }
 
+/// Template values for entries, keyed by their path
+pub type EntryTemplateValues = HashMap<PathBuf, TemplateValues>;
+
 #[derive(Debug, Default)]
-pub struct DirEntryData(Vec<jwalk::DirEntry<((), ())>>);
+pub struct DirEntryData {
+    entries: Vec<jwalk::DirEntry<((), ())>>,
+    /// values the filters extracted from the entries for templates
+    template_values: EntryTemplateValues,
+}
 
 impl IntoIterator for DirEntryData {
     type Item = jwalk::DirEntry<((), ())>;
@@ -20,27 +35,39 @@ impl IntoIterator for DirEntryData {
     type IntoIter = IntoIter<DirEntry<((), ())>>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.entries.into_iter()
     }
 }
 
 impl DirEntryData {
     pub fn print_entries(&self) {
-        let count = self.0.len();
-        self.0.iter().for_each(|f| {
+        let count = self.entries.len();
+        self.entries.iter().for_each(|f| {
             println!("{f:?}");
         });
         println!("Total entry count: {count}");
     }
 
-    pub(crate) fn iter(&self) -> Iter<'_, jwalk::DirEntry<((), ())>> {
-        self.0.iter()
+    pub fn iter(&self) -> Iter<'_, jwalk::DirEntry<((), ())>> {
+        self.entries.iter()
+    }
+
+    /// Adds an entry together with the template values extracted from it
+    pub(crate) fn push(&mut self, entry: jwalk::DirEntry<((), ())>, values: TemplateValues) {
+        if !values.is_empty() {
+            _ = self.template_values.insert(entry.path(), values);
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn template_values(&self) -> &EntryTemplateValues {
+        &self.template_values
     }
 }
 
 impl Display for DirEntryData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let last_five = self.0.iter().rev().take(5).collect_vec();
+        let last_five = self.entries.iter().rev().take(5).collect_vec();
         write!(
             f,
             "
@@ -56,7 +83,10 @@ impl Display for DirEntryData {
 
 impl From<Vec<jwalk::DirEntry<((), ())>>> for DirEntryData {
     fn from(value: Vec<jwalk::DirEntry<((), ())>>) -> Self {
-        Self(value)
+        Self {
+            entries: value,
+            template_values: EntryTemplateValues::new(),
+        }
     }
 }
 
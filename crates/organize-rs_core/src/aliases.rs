@@ -1,14 +1,26 @@
 //! Aliases for folders that can be used in the config file
 //! to keep it DRY.
 
+use std::{path::PathBuf, slice::Iter};
+
+use displaydoc::Display;
 use serde::{Deserialize, Serialize};
 
-use crate::aliases;
+use crate::{
+    actions::ActionKind,
+    error::{ConfigErrorKind, OrganizeResult},
+    filters::FilterKind,
+    locations::LocationCollection,
+    rules::Rule,
+    templating::find_templates,
+};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display)]
 pub enum ItemKind {
+    /// extension
     #[serde(rename = "extension")]
     Extension,
+    /// folder
     #[serde(rename = "folder")]
     Location,
 }
@@ -49,6 +61,128 @@ impl Aliases {
     pub fn with_aliases(aliases: Vec<Alias>) -> Self {
         Self(aliases)
     }
+
+    pub fn iter(&self) -> Iter<'_, Alias> {
+        self.0.iter()
+    }
+
+    /// Returns the alias called `name`
+    pub fn get(&self, name: &str) -> Option<&Alias> {
+        self.0.iter().find(|alias| alias.name() == name)
+    }
+
+    /// Substitutes the aliases used in `rule`
+    ///
+    /// A location containing a folder alias, e.g. `{alias.downloads}`, is
+    /// replaced by one location per folder of the alias. Extension aliases
+    /// in the `extension` filter are replaced by their extensions.
+    /// Destinations of actions can only use folder aliases with a single
+    /// folder.
+    ///
+    /// # Errors
+    ///
+    /// If an alias is not defined, is of the wrong kind or is used as a
+    /// destination with more than one folder
+    pub fn expand_rule(&self, rule: &mut Rule) -> OrganizeResult<()> {
+        let mut locations = vec![];
+        for location in rule.locations_mut().drain(..) {
+            let path = location.path().to_string_lossy().to_string();
+            match self.expand(&path, ItemKind::Location)? {
+                Some(paths) => locations.extend(
+                    paths
+                        .into_iter()
+                        .map(|path| location.with_path(PathBuf::from(path))),
+                ),
+                None => locations.push(location),
+            }
+        }
+        *rule.locations_mut() = LocationCollection::from_vec(locations);
+
+        for filter_group in rule.filters_mut().iter_mut() {
+            for filter in &mut filter_group.filters {
+                if let FilterKind::Extension { exts } = filter {
+                    let mut expanded = vec![];
+                    for ext in exts.drain(..) {
+                        match self.expand(&ext, ItemKind::Extension)? {
+                            Some(items) => expanded.extend(items),
+                            None => expanded.push(ext),
+                        }
+                    }
+                    *exts = expanded;
+                }
+            }
+        }
+
+        for container in rule.actions_mut().iter_mut() {
+            match &mut container.action {
+                ActionKind::Copy { dst, .. }
+                | ActionKind::Move { dst, .. }
+                | ActionKind::Symlink { dst } => self.expand_destination(dst)?,
+                ActionKind::Write { file, .. } => self.expand_destination(file)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Substitutes a single-folder alias in a destination
+    fn expand_destination(&self, destination: &mut PathBuf) -> OrganizeResult<()> {
+        let text = destination.to_string_lossy().to_string();
+        let Some(mut paths) = self.expand(&text, ItemKind::Location)? else {
+            return Ok(());
+        };
+
+        if paths.len() != 1 {
+            return Err(ConfigErrorKind::AmbiguousAliasDestination {
+                destination: text,
+                count: paths.len(),
+            }
+            .into());
+        }
+        *destination = PathBuf::from(paths.remove(0));
+
+        Ok(())
+    }
+
+    /// Returns all combinations of the items of the aliases used in `text`,
+    /// or `None` if it doesn't use any aliases
+    fn expand(&self, text: &str, kind: ItemKind) -> OrganizeResult<Option<Vec<String>>> {
+        let templates = find_templates(text)?;
+        let mut expanded = vec![text.to_string()];
+        let mut found = false;
+
+        for template in templates {
+            let content = template.trim_matches(|c: char| c == '{' || c == '}' || c == ' ');
+            let Some(name) = content.strip_prefix("alias.") else {
+                continue;
+            };
+            found = true;
+
+            let alias = self
+                .get(name)
+                .ok_or_else(|| ConfigErrorKind::AliasNotDefined(name.to_string()))?;
+            if alias.kind() != kind {
+                return Err(ConfigErrorKind::AliasKindMismatch {
+                    name: name.to_string(),
+                    expected: kind,
+                }
+                .into());
+            }
+
+            expanded = expanded
+                .iter()
+                .flat_map(|text| {
+                    alias
+                        .items()
+                        .iter()
+                        .map(|item| text.replace(template, item))
+                })
+                .collect();
+        }
+
+        Ok(found.then_some(expanded))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -63,6 +197,14 @@ impl Alias {
         self.name.as_ref()
     }
 
+    pub fn kind(&self) -> ItemKind {
+        self.kind
+    }
+
+    pub fn items(&self) -> &[String] {
+        self.items.as_ref()
+    }
+
     pub fn folders(&self) -> &[String] {
         self.items.as_ref()
     }
@@ -82,8 +224,44 @@ mod tests {
 
     use insta::assert_yaml_snapshot;
 
+    use crate::{
+        config::{ConfigFileFormat, OrganizeConfig},
+        error::OrganizeErrorKind,
+    };
+
     use super::*;
 
+    const ALIASES: &str = r#"
+aliases:
+  - name: downloads
+    kind: folder
+    items:
+      - ~/Downloads/
+      - /tmp/downloads/
+  - name: archive
+    kind: folder
+    items:
+      - ~/Archive
+  - name: images
+    kind: extension
+    items:
+      - png
+      - jpg
+"#;
+
+    fn expand(rules: &str) -> OrganizeResult<OrganizeConfig> {
+        let mut config =
+            OrganizeConfig::load_from_string(&format!("{ALIASES}{rules}"), ConfigFileFormat::Yaml);
+        config.expand_aliases().map(|_| config)
+    }
+
+    fn config_error(rules: &str) -> ConfigErrorKind {
+        match expand(rules).unwrap_err().into_inner() {
+            OrganizeErrorKind::Config(err) => err,
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
     #[test]
     fn test_yaml_aliases_parsing_passed() {
         let yaml = r#"
@@ -138,4 +316,95 @@ mod tests {
             - png
         "###);
     }
+
+    #[test]
+    fn test_expand_aliases_passes() {
+        let config = expand(
+            r#"
+rules:
+  - name: Images
+    locations:
+      - !recursive
+        path: "{alias.downloads}images"
+        max_depth: 2
+        target: files
+    filter_groups:
+      - filters:
+          - !extension
+            exts:
+              - "{alias.images}"
+              - gif
+        results: include
+        match: all
+    actions:
+      - mode: preview
+        action: !move
+          dst: "{alias.archive}/images/"
+"#,
+        )
+        .unwrap();
+
+        insta::assert_debug_snapshot!(config.rules()[0].locations().iter().map(|location| location.path()).collect::<Vec<_>>(), @r###"
+        [
+            "~/Downloads/images",
+            "/tmp/downloads/images",
+        ]
+        "###);
+        let rule = &config.rules()[0];
+        assert!(matches!(
+            rule.filters()[0].filters.as_slice(),
+            [FilterKind::Extension { exts }] if exts == &["png", "jpg", "gif"]
+        ));
+        assert!(matches!(
+            &rule.actions()[0].action,
+            ActionKind::Move { dst, .. } if dst == &PathBuf::from("~/Archive/images/")
+        ));
+    }
+
+    #[test]
+    fn test_expand_multi_folder_alias_as_destination_fails() {
+        let err = config_error(
+            r#"
+rules:
+  - name: Sort
+    actions:
+      - mode: preview
+        action: !copy
+          dst: "{alias.downloads}"
+"#,
+        );
+
+        assert!(matches!(
+            err,
+            ConfigErrorKind::AmbiguousAliasDestination { count: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_expand_undefined_alias_fails() {
+        let err = config_error(
+            r#"
+rules:
+  - name: Sort
+    locations:
+      - !default_settings "{alias.missing}"
+"#,
+        );
+
+        assert!(matches!(err, ConfigErrorKind::AliasNotDefined(name) if name == "missing"));
+    }
+
+    #[test]
+    fn test_expand_alias_of_wrong_kind_fails() {
+        let err = config_error(
+            r#"
+rules:
+  - name: Sort
+    locations:
+      - !default_settings "{alias.images}"
+"#,
+        );
+
+        insta::assert_snapshot!(err.to_string(), @"alias `images` can't be used here, a folder alias is expected");
+    }
 }
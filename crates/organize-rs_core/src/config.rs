@@ -2,7 +2,10 @@
 
 use crate::{
     aliases::Aliases,
+    config::migrations::ConfigMigrator,
     error::{ConfigErrorKind, OrganizeResult},
+    filesystem::expand_path,
+    filters::FilterKind,
     rules::{Rule, Rules},
 };
 use std::io::Write;
@@ -13,15 +16,16 @@ use clap::ValueEnum;
 
 use displaydoc::Display;
 use ron::ser::PrettyConfig;
-use semver::{BuildMetadata, Prerelease, Version};
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
+pub mod migrations;
+
 pub static CONFIG_TEMPLATE_YAML: &str = include_str!("../config/config_template.yaml");
 
 /// Formats that we support for our Config files
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Display)]
-// TODO: do we want to chose explicitly or derive it from extension?
-// #[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
 pub enum ConfigFileFormat {
     /// json
     Json,
@@ -49,27 +53,49 @@ impl FromStr for ConfigFileFormat {
     }
 }
 
+impl ConfigFileFormat {
+    /// Derives the format of a config file from its extension
+    pub fn from_path(path: impl AsRef<Path>) -> OrganizeResult<Self> {
+        let path = path.as_ref();
+        let Some(ext) = path.extension() else {
+            return Err(ConfigErrorKind::ConfigFileExtensionMissing(path.to_path_buf()).into());
+        };
+
+        ext.to_string_lossy().parse()
+    }
+}
+
 impl Default for ConfigFileFormat {
     fn default() -> Self {
         Self::Ron
     }
 }
 
+/// Version of the config schema this version of organize reads and writes
+pub const CURRENT_CONFIG_VERSION: Version = Version::new(1, 0, 0);
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OrganizeConfigVersion(Version);
 
+impl OrganizeConfigVersion {
+    pub fn version(&self) -> &Version {
+        &self.0
+    }
+}
+
 impl Default for OrganizeConfigVersion {
     fn default() -> Self {
-        Self(Version {
-            major: 1,
-            minor: 0,
-            patch: 0,
-            pre: Prerelease::EMPTY,
-            build: BuildMetadata::EMPTY,
-        })
+        Self(CURRENT_CONFIG_VERSION)
     }
 }
 
+/// Just the version of a config, to decide how to load the rest of it
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename = "OrganizeConfig")]
+struct VersionedConfig {
+    version: OrganizeConfigVersion,
+}
+
 /// Organize Configuration
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 // #[serde(deny_unknown_fields)]
@@ -97,56 +123,154 @@ impl OrganizeConfig {
         }
     }
 
+    /// Parses a config in the given `format`
+    ///
+    /// # Panics
+    ///
+    /// If the config can't be parsed, use [`OrganizeConfig::parse`]
+    /// to handle the error instead
     pub fn load_from_string(string: &str, format: ConfigFileFormat) -> Self {
-        match format {
-            ConfigFileFormat::Yaml => {
-                serde_yaml::from_str(string).expect("config file parsing shouldn't fail")
-            }
-            ConfigFileFormat::Json => todo!(),
-            ConfigFileFormat::Toml => todo!(),
-            ConfigFileFormat::Ron => todo!(),
-        }
+        Self::parse(string, format).expect("config file parsing shouldn't fail")
     }
 
-    pub fn load_from_file(path: impl AsRef<Path>) -> Self {
-        let file = std::fs::File::open(path.as_ref()).expect("opening file shouldn't fail");
-
-        let ext = <ConfigFileFormat as std::str::FromStr>::from_str(
-            path.as_ref()
-                .extension()
-                .expect("getting file extension shouldn't fail")
-                .to_str()
-                .expect("conversion from OsStr to String shouldn't fail"),
-        )
-        .unwrap();
+    /// Parses a config in the given `format`
+    ///
+    /// # Errors
+    ///
+    /// If the config isn't valid in the given `format`
+    pub fn parse(string: &str, format: ConfigFileFormat) -> OrganizeResult<Self> {
+        Ok(Self::deserialize_from(string, format)?)
+    }
 
-        match ext {
+    fn deserialize_from(string: &str, format: ConfigFileFormat) -> Result<Self, ConfigErrorKind> {
+        let config = match format {
             ConfigFileFormat::Yaml => {
-                serde_yaml::from_reader(file).expect("config file parsing shouldn't fail")
+                serde_yaml::from_str(string).map_err(ConfigErrorKind::YamlError)?
             }
             ConfigFileFormat::Json => {
-                serde_json::from_reader(file).expect("config file parsing shouldn't fail")
+                serde_json::from_str(string).map_err(ConfigErrorKind::JsonError)?
+            }
+            ConfigFileFormat::Toml => {
+                toml::from_str(string).map_err(ConfigErrorKind::TomlDeserializeError)?
             }
             ConfigFileFormat::Ron => {
-                todo!("not fully supported (yet)");
-                // TODO: ron deser support
-                // ron::de::from_reader(file).expect("config file parsing shouldn't fail")
+                ron::from_str(string).map_err(ConfigErrorKind::RonDeserializeError)?
             }
-            ConfigFileFormat::Toml => {
-                todo!("not fully supported (yet)");
-                // TODO: toml deser support
-                // toml::from_str(
-                //     std::fs::read_to_string(path)
-                //         .expect("reading config file to string shouldn't fail")
-                //         .as_str(),
-                // )
-                // .expect("config file parsing shouldn't fail")
+        };
+
+        Ok(config)
+    }
+
+    /// Loads a config file, its format is derived from the extension
+    ///
+    /// Configs of older versions are upgraded with the registered
+    /// [`migrations::MIGRATIONS`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, isn't a valid config or its version isn't
+    /// supported, parsing errors carry the path, the position in the file
+    /// and the offending key
+    pub fn load_from_file(path: impl AsRef<Path>) -> OrganizeResult<Self> {
+        Self::load_with_migrator(path, &ConfigMigrator::default())
+    }
+
+    pub(crate) fn load_with_migrator(
+        path: impl AsRef<Path>,
+        migrator: &ConfigMigrator<'_>,
+    ) -> OrganizeResult<Self> {
+        let path = path.as_ref();
+        let format = ConfigFileFormat::from_path(path)?;
+        let content = read_config_file(path)?;
+
+        match Self::version_of(&content, format) {
+            Some(version) if version > CURRENT_CONFIG_VERSION => {
+                Err(ConfigErrorKind::UnsupportedConfigVersion {
+                    version,
+                    supported: CURRENT_CONFIG_VERSION,
+                }
+                .into())
             }
+            Some(version) if version < CURRENT_CONFIG_VERSION => {
+                let mut raw = migrations::raw_config(&content, format)?;
+                migrator.migrate(&mut raw, &version)?;
+
+                serde_json::from_value(raw).map_err(|err| {
+                    invalid_config_file(path, &content, ConfigErrorKind::JsonError(err)).into()
+                })
+            }
+            // invalid configs are reported by the regular deserialization
+            _ => Self::deserialize_from(&content, format)
+                .map_err(|source| invalid_config_file(path, &content, source).into()),
+        }
+    }
+
+    /// Reads the version of the config file at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or parsed
+    pub fn version_of_file(path: impl AsRef<Path>) -> OrganizeResult<Version> {
+        let path = path.as_ref();
+        let format = ConfigFileFormat::from_path(path)?;
+        let content = read_config_file(path)?;
+
+        match Self::version_of(&content, format) {
+            Some(version) => Ok(version),
+            None => Self::deserialize_from(&content, format)
+                .map(|config| config.version().clone())
+                .map_err(|source| invalid_config_file(path, &content, source).into()),
         }
     }
 
+    fn version_of(string: &str, format: ConfigFileFormat) -> Option<Version> {
+        let versioned: VersionedConfig = match format {
+            ConfigFileFormat::Yaml => serde_yaml::from_str(string).ok()?,
+            ConfigFileFormat::Json => serde_json::from_str(string).ok()?,
+            ConfigFileFormat::Toml => toml::from_str(string).ok()?,
+            ConfigFileFormat::Ron => ron::from_str(string).ok()?,
+        };
+
+        Some(versioned.version.0)
+    }
+
+    /// Serializes the config in the given `format`
+    ///
+    /// # Errors
+    ///
+    /// If the config can't be represented in the given `format`
+    pub fn to_string_as(&self, format: ConfigFileFormat) -> OrganizeResult<String> {
+        to_config_string(self, format)
+    }
+
+    /// Writes the config to `path`, its format is derived from the extension
+    ///
+    /// # Errors
+    ///
+    /// If the format isn't supported, the file exists and `overwrite` is
+    /// `false` or the config can't be written
     pub fn write_to_file(&self, path: impl AsRef<Path>, overwrite: bool) -> OrganizeResult<()> {
-        let file = match (File::open(path.as_ref()).is_ok(), overwrite) {
+        let format = ConfigFileFormat::from_path(path.as_ref())?;
+        self.write_to_file_as(path, format, overwrite)
+    }
+
+    /// Writes the config to `path` in the given `format`, regardless of
+    /// the extension of `path`
+    ///
+    /// # Errors
+    ///
+    /// If the file exists and `overwrite` is `false` or the config
+    /// can't be written
+    pub fn write_to_file_as(
+        &self,
+        path: impl AsRef<Path>,
+        format: ConfigFileFormat,
+        overwrite: bool,
+    ) -> OrganizeResult<()> {
+        // serialize first, so a failure doesn't leave an empty file behind
+        let content = self.to_string_as(format)?;
+
+        let mut file = match (File::open(path.as_ref()).is_ok(), overwrite) {
             (true | false, true) | (false, false) => File::create(path.as_ref())?,
             (true, false) => {
                 return Err(crate::error::ConfigErrorKind::ConfigFileAlreadyExists(
@@ -156,36 +280,7 @@ impl OrganizeConfig {
             }
         };
 
-        let ext = <ConfigFileFormat as std::str::FromStr>::from_str(
-            path.as_ref()
-                .extension()
-                .expect("getting file extension shouldn't fail")
-                .to_str()
-                .expect("conversion from OsStr to String shouldn't fail"),
-        )
-        .unwrap();
-
-        match ext {
-            ConfigFileFormat::Ron => {
-                ron::ser::to_writer_pretty(file, &self, PrettyConfig::default())
-                    .map_err(ConfigErrorKind::RonError)?
-            }
-            ConfigFileFormat::Yaml => {
-                serde_yaml::to_writer(file, self).map_err(ConfigErrorKind::YamlError)?
-            }
-            ConfigFileFormat::Json => {
-                serde_json::to_writer_pretty(file, self).map_err(ConfigErrorKind::JsonError)?
-            }
-            ConfigFileFormat::Toml => {
-                todo!("not fully supported (yet)");
-                // TODO: toml ser support
-                // write!(
-                //     file,
-                //     "{}",
-                //     toml::to_string_pretty(self).map_err(ConfigErrorKind::TomlSerializeError)?
-                // )?
-            }
-        }
+        write!(file, "{content}")?;
 
         Ok(())
     }
@@ -201,13 +296,385 @@ impl OrganizeConfig {
     pub fn rules(&self) -> &Rules {
         &self.rules
     }
+
+    pub fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    /// Substitutes the aliases used in the rules, see [`Aliases::expand_rule`]
+    ///
+    /// # Errors
+    ///
+    /// If an alias can't be substituted
+    pub fn expand_aliases(&mut self) -> OrganizeResult<()> {
+        for rule in self.rules.iter_mut() {
+            self.aliases.expand_rule(rule)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the filters of all rules can be set up, see
+    /// [`FilterKind::check`]
+    ///
+    /// # Errors
+    ///
+    /// If a regular expression or script of a filter is invalid
+    pub fn check_filters(&self) -> OrganizeResult<()> {
+        for rule in self.rules.iter() {
+            for filter_group in rule.filters().iter() {
+                for filter in filter_group.filters() {
+                    filter.check()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands `~` and environment variables in the paths of the locations,
+    /// script filters and actions, see [`expand_path`]
+    ///
+    /// # Errors
+    ///
+    /// If a path can't be expanded
+    pub fn expand_paths(&mut self) -> OrganizeResult<()> {
+        for rule in self.rules.iter_mut() {
+            for location in rule.locations_mut().iter_mut() {
+                *location = location.with_path(expand_path(location.path())?);
+            }
+            for filter_group in rule.filters_mut().iter_mut() {
+                for filter in &mut filter_group.filters {
+                    if let FilterKind::Script {
+                        path: Some(path), ..
+                    } = filter
+                    {
+                        *path = expand_path(path)?;
+                    }
+                }
+            }
+            for container in rule.actions_mut().iter_mut() {
+                container.action.expand_paths()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn version(&self) -> &Version {
+        self.version.version()
+    }
+}
+
+fn read_config_file(path: &Path) -> OrganizeResult<String> {
+    Ok(std::fs::read_to_string(path).map_err(|source| {
+        ConfigErrorKind::ReadingConfigFileFailed {
+            path: path.to_path_buf(),
+            source,
+        }
+    })?)
+}
+
+/// Attaches the path and the position of the error within `content`
+fn invalid_config_file(path: &Path, content: &str, source: ConfigErrorKind) -> ConfigErrorKind {
+    let (position, message) = match &source {
+        ConfigErrorKind::YamlError(err) => (
+            err.location()
+                .map(|location| (location.line(), location.column())),
+            err.to_string(),
+        ),
+        ConfigErrorKind::JsonError(err) => (Some((err.line(), err.column())), err.to_string()),
+        ConfigErrorKind::TomlDeserializeError(err) => (
+            err.span().map(|span| line_and_column(content, span.start)),
+            err.message().to_string(),
+        ),
+        ConfigErrorKind::RonDeserializeError(err) => (
+            Some((err.position.line, err.position.col)),
+            err.code.to_string(),
+        ),
+        _ => (None, source.to_string()),
+    };
+    // positions of 0 are reported for errors without a position
+    let position = position.filter(|(line, _)| *line > 0);
+
+    ConfigErrorKind::InvalidConfigFile {
+        path: path.to_path_buf(),
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        key: offending_key(&message),
+        source: Box::new(source),
+    }
+}
+
+/// Converts a byte offset into `content` to a line and column, starting at 1
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Extracts the field or variant from messages like ``unknown variant `name` ``
+fn offending_key(message: &str) -> Option<String> {
+    ["field `", "variant `", "named `"]
+        .iter()
+        .find_map(|prefix| {
+            let (_, rest) = message.split_once(prefix)?;
+            let (key, _) = rest.split_once('`')?;
+            Some(key.to_string())
+        })
+}
+
+/// Serializes `value`, e.g. a snippet of a config, in the given `format`
+///
+/// # Errors
+///
+/// If `value` can't be represented in the given `format`, TOML
+/// documents e.g. need a table at their root
+pub fn to_config_string<T>(value: &T, format: ConfigFileFormat) -> OrganizeResult<String>
+where
+    T: Serialize + ?Sized,
+{
+    let string = match format {
+        ConfigFileFormat::Ron => ron::ser::to_string_pretty(value, PrettyConfig::default())
+            .map_err(ConfigErrorKind::RonSerializeError)?,
+        ConfigFileFormat::Yaml => {
+            serde_yaml::to_string(value).map_err(ConfigErrorKind::YamlError)?
+        }
+        ConfigFileFormat::Json => {
+            serde_json::to_string_pretty(value).map_err(ConfigErrorKind::JsonError)?
+        }
+        ConfigFileFormat::Toml => {
+            // `toml` can't serialize struct variants (e.g. `!recursive` locations)
+            // directly, so we go through their JSON representation
+            let mut value = serde_json::to_value(value).map_err(ConfigErrorKind::JsonError)?;
+            remove_null_values(&mut value);
+            toml::to_string_pretty(&value).map_err(ConfigErrorKind::TomlSerializeError)?
+        }
+    };
+
+    Ok(string)
+}
+
+/// Removes the `null` values of unset optional fields, TOML can't represent them
+fn remove_null_values(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(remove_null_values);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(remove_null_values),
+        _ => (),
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    use crate::error::{OrganizeError, OrganizeErrorKind};
+
     use super::*;
 
+    #[rstest]
+    #[case(ConfigFileFormat::Json)]
+    #[case(ConfigFileFormat::Toml)]
+    #[case(ConfigFileFormat::Yaml)]
+    #[case(ConfigFileFormat::Ron)]
+    fn test_config_round_trip_passes(#[case] format: ConfigFileFormat) {
+        let config = OrganizeConfig::load_from_string(CONFIG_TEMPLATE_YAML, ConfigFileFormat::Yaml);
+        let dir = tempdir().unwrap();
+        let path = dir
+            .path()
+            .join(format!("config.{}", format.to_string().to_lowercase()));
+
+        config.write_to_file(&path, false).unwrap();
+        let loaded = OrganizeConfig::load_from_file(&path).unwrap();
+
+        assert_eq!(
+            OrganizeConfig::version_of_file(&path).unwrap(),
+            CURRENT_CONFIG_VERSION
+        );
+
+        assert_eq!(
+            loaded.to_string_as(ConfigFileFormat::Yaml).unwrap(),
+            config.to_string_as(ConfigFileFormat::Yaml).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(ConfigFileFormat::Json)]
+    #[case(ConfigFileFormat::Toml)]
+    #[case(ConfigFileFormat::Yaml)]
+    #[case(ConfigFileFormat::Ron)]
+    fn test_snippet_to_config_string_passes(#[case] format: ConfigFileFormat) {
+        #[derive(Serialize)]
+        struct Snippet<'a> {
+            rules: &'a Rules,
+        }
+
+        let config = OrganizeConfig::load_from_string(CONFIG_TEMPLATE_YAML, ConfigFileFormat::Yaml);
+
+        let snippet = to_config_string(
+            &Snippet {
+                rules: config.rules(),
+            },
+            format,
+        )
+        .unwrap();
+        let parsed = OrganizeConfig::parse(&snippet, format).unwrap();
+
+        assert_eq!(
+            to_config_string(parsed.rules(), ConfigFileFormat::Yaml).unwrap(),
+            to_config_string(config.rules(), ConfigFileFormat::Yaml).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_snippet_without_table_to_toml_fails() {
+        let config = OrganizeConfig::load_from_string(CONFIG_TEMPLATE_YAML, ConfigFileFormat::Yaml);
+
+        let error = to_config_string(config.rules(), ConfigFileFormat::Toml)
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            error,
+            OrganizeErrorKind::Config(ConfigErrorKind::TomlSerializeError(_))
+        ));
+    }
+
+    #[rstest]
+    #[case(ConfigFileFormat::Json)]
+    #[case(ConfigFileFormat::Toml)]
+    #[case(ConfigFileFormat::Yaml)]
+    #[case(ConfigFileFormat::Ron)]
+    fn test_parse_invalid_config_fails(#[case] format: ConfigFileFormat) {
+        let error = OrganizeConfig::parse("rules = [[ 1", format)
+            .unwrap_err()
+            .into_inner();
+
+        let expected = match format {
+            ConfigFileFormat::Json => {
+                matches!(
+                    error,
+                    OrganizeErrorKind::Config(ConfigErrorKind::JsonError(_))
+                )
+            }
+            ConfigFileFormat::Toml => matches!(
+                error,
+                OrganizeErrorKind::Config(ConfigErrorKind::TomlDeserializeError(_))
+            ),
+            ConfigFileFormat::Yaml => {
+                matches!(
+                    error,
+                    OrganizeErrorKind::Config(ConfigErrorKind::YamlError(_))
+                )
+            }
+            ConfigFileFormat::Ron => matches!(
+                error,
+                OrganizeErrorKind::Config(ConfigErrorKind::RonDeserializeError(_))
+            ),
+        };
+        assert!(expected, "unexpected error: {error:?}");
+    }
+
+    #[rstest]
+    #[case(
+        "config.yaml",
+        "rules:\n  - name: test\n    filter_groups:\n      - filters:\n          - colour\n",
+        5,
+        "colour"
+    )]
+    #[case(
+        "config.json",
+        "{\n  \"rules\": [\n    { \"filter_groups\": [{ \"filters\": [\"colour\"] }] }\n  ]\n}",
+        3,
+        "colour"
+    )]
+    #[case("config.toml", "[[rules]]\nname = 'test'\nenabled = 'yes'\n", 3, "")]
+    #[case(
+        "config.ron",
+        "(\n  rules: [\n    (filter_groups: [(filters: [colour])]),\n  ],\n)",
+        3,
+        "colour"
+    )]
+    fn test_load_invalid_config_file_fails(
+        #[case] name: &str,
+        #[case] content: &str,
+        #[case] expected_line: usize,
+        #[case] expected_key: &str,
+    ) {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join(name);
+        std::fs::write(&config_path, content).unwrap();
+
+        let error = OrganizeConfig::load_from_file(&config_path)
+            .unwrap_err()
+            .into_inner();
+
+        let OrganizeErrorKind::Config(ConfigErrorKind::InvalidConfigFile {
+            path, line, key, ..
+        }) = error
+        else {
+            panic!("unexpected error: {error:?}");
+        };
+        assert_eq!(path, config_path);
+        assert_eq!(line, Some(expected_line));
+        assert_eq!(key.unwrap_or_default(), expected_key);
+    }
+
+    #[test]
+    fn test_load_newer_config_version_fails() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "version: 99.0.0\nrules: []\n").unwrap();
+
+        let error = OrganizeConfig::load_from_file(&path)
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            error,
+            OrganizeErrorKind::Config(ConfigErrorKind::UnsupportedConfigVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_missing_config_file_fails() {
+        let dir = tempdir().unwrap();
+
+        let error = OrganizeConfig::load_from_file(dir.path().join("missing.yaml"))
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            error,
+            OrganizeErrorKind::Config(ConfigErrorKind::ReadingConfigFileFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_write_to_existing_file_fails() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "").unwrap();
+
+        let result = OrganizeConfig::default().write_to_file(&path, false);
+
+        assert!(matches!(
+            result.map_err(OrganizeError::into_inner),
+            Err(OrganizeErrorKind::Config(
+                ConfigErrorKind::ConfigFileAlreadyExists(_)
+            ))
+        ));
+    }
+
     #[test]
     fn test_default_config_serialization_passes() {
         let mut config = OrganizeConfig::default();
@@ -0,0 +1,387 @@
+//! Migrations of configs written for older config versions
+//!
+//! Migrations work on the raw config, so they can upgrade configs that
+//! can't be deserialized with the current schema anymore. Enum variants
+//! are represented by maps with the variant name as the only key, e.g.
+//! `{"recursive": {"path": "..."}}`, unit variants by their name.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use semver::Version;
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{ConfigFileFormat, OrganizeConfig, CURRENT_CONFIG_VERSION},
+    error::{ConfigErrorKind, OrganizeResult},
+};
+
+/// A single step, that upgrades a raw config from one version to the next
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// version the migration upgrades from
+    pub from: Version,
+    /// version the migration upgrades to
+    pub to: Version,
+    /// upgrades the raw config in place, returns the reason if it fails
+    pub migrate: fn(&mut Value) -> Result<(), String>,
+}
+
+/// Migrations that are run to upgrade older configs, each upgrades from
+/// the version the previous one upgraded to
+pub static MIGRATIONS: &[Migration] = &[];
+
+/// Upgrades raw configs by running a chain of [`Migration`]s
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigMigrator<'a> {
+    migrations: &'a [Migration],
+}
+
+impl Default for ConfigMigrator<'static> {
+    fn default() -> Self {
+        Self::new(MIGRATIONS)
+    }
+}
+
+impl<'a> ConfigMigrator<'a> {
+    pub fn new(migrations: &'a [Migration]) -> Self {
+        Self { migrations }
+    }
+
+    /// Upgrades `config` from `version` to the current config version
+    ///
+    /// # Errors
+    ///
+    /// If there is no chain of migrations to the current version or a
+    /// migration fails
+    pub fn migrate(&self, config: &mut Value, version: &Version) -> OrganizeResult<()> {
+        let mut version = version.clone();
+
+        while version < CURRENT_CONFIG_VERSION {
+            let Some(migration) = self
+                .migrations
+                .iter()
+                .find(|migration| migration.from == version)
+            else {
+                return Err(ConfigErrorKind::MigrationNotFound(version).into());
+            };
+
+            (migration.migrate)(config).map_err(|reason| ConfigErrorKind::MigrationFailed {
+                from: migration.from.clone(),
+                to: migration.to.clone(),
+                reason,
+            })?;
+            version = migration.to.clone();
+        }
+
+        if let Value::Object(map) = config {
+            _ = map.insert("version".to_string(), Value::String(version.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a config into its raw representation, that migrations work on
+///
+/// # Errors
+///
+/// If the config can't be parsed or migrations aren't supported for
+/// the `format`
+pub fn raw_config(string: &str, format: ConfigFileFormat) -> OrganizeResult<Value> {
+    let value = match format {
+        ConfigFileFormat::Json => {
+            serde_json::from_str(string).map_err(ConfigErrorKind::JsonError)?
+        }
+        ConfigFileFormat::Yaml => {
+            let value: serde_yaml::Value =
+                serde_yaml::from_str(string).map_err(ConfigErrorKind::YamlError)?;
+            yaml_to_raw(value)
+        }
+        ConfigFileFormat::Toml => {
+            let value: toml::Value =
+                toml::from_str(string).map_err(ConfigErrorKind::TomlDeserializeError)?;
+            serde_json::to_value(value).map_err(ConfigErrorKind::JsonError)?
+        }
+        // RON doesn't keep the names of enum variants in its untyped values
+        ConfigFileFormat::Ron => return Err(ConfigErrorKind::MigrationNotSupported(format).into()),
+    };
+
+    Ok(value)
+}
+
+/// Converts YAML tags of enum variants to the representation migrations
+/// work on
+fn yaml_to_raw(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Tagged(tagged) => {
+            let variant = tagged.tag.to_string().trim_start_matches('!').to_string();
+            match yaml_to_raw(tagged.value) {
+                Value::Null => Value::String(variant),
+                value => Value::Object(Map::from_iter([(variant, value)])),
+            }
+        }
+        serde_yaml::Value::Sequence(values) => {
+            Value::Array(values.into_iter().map(yaml_to_raw).collect())
+        }
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let key = match key {
+                        serde_yaml::Value::String(key) => key,
+                        key => serde_yaml::to_string(&key).ok()?.trim().to_string(),
+                    };
+                    Some((key, yaml_to_raw(value)))
+                })
+                .collect(),
+        ),
+        // scalars are represented the same way
+        value => serde_json::to_value(value).unwrap_or_default(),
+    }
+}
+
+/// A config file, that has been upgraded to the current version
+#[derive(Debug, Clone)]
+pub struct MigratedConfigFile {
+    /// version of the config before the migration
+    pub from: Version,
+    /// copy of the config file before the migration
+    pub backup: PathBuf,
+}
+
+impl ConfigMigrator<'_> {
+    /// Upgrades the config file at `path` to the current config version in
+    /// place, the original file is kept with a `.bak` extension
+    ///
+    /// Existing backups are never overwritten, if `<path>.bak` already
+    /// exists the backup is named `<path>.1.bak`, `<path>.2.bak` and so on.
+    ///
+    /// Returns `None` if the config is up to date. Comments and formatting
+    /// of the config file are not preserved.
+    ///
+    /// # Errors
+    ///
+    /// If the config can't be loaded or migrated or the files can't be written
+    pub fn migrate_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> OrganizeResult<Option<MigratedConfigFile>> {
+        let path = path.as_ref();
+        let from = OrganizeConfig::version_of_file(path)?;
+        if from > CURRENT_CONFIG_VERSION {
+            return Err(ConfigErrorKind::UnsupportedConfigVersion {
+                version: from,
+                supported: CURRENT_CONFIG_VERSION,
+            }
+            .into());
+        }
+        if from == CURRENT_CONFIG_VERSION {
+            return Ok(None);
+        }
+
+        let config = OrganizeConfig::load_with_migrator(path, self)?;
+
+        let backup = Self::write_backup(path)?;
+
+        config.write_to_file(path, true)?;
+
+        Ok(Some(MigratedConfigFile { from, backup }))
+    }
+
+    /// Copies the file at `path` to the first backup path that doesn't
+    /// exist yet and returns it
+    fn write_backup(path: &Path) -> OrganizeResult<PathBuf> {
+        let mut original = File::open(path)?;
+        for n in 0_usize.. {
+            let mut backup = path.as_os_str().to_owned();
+            if n > 0 {
+                backup.push(format!(".{n}"));
+            }
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&backup)
+            {
+                Ok(mut file) => {
+                    _ = std::io::copy(&mut original, &mut file)?;
+                    return Ok(backup);
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        unreachable!("there is always a backup path that doesn't exist")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::error::OrganizeErrorKind;
+
+    use super::*;
+
+    fn rename_rule_names(config: &mut Value) -> Result<(), String> {
+        let rules = config
+            .get_mut("rules")
+            .and_then(Value::as_array_mut)
+            .ok_or("rules are missing")?;
+        for rule in rules {
+            let title = rule
+                .as_object_mut()
+                .and_then(|rule| rule.remove("title"))
+                .ok_or("rule title is missing")?;
+            rule["name"] = title;
+        }
+        Ok(())
+    }
+
+    fn test_migrations() -> Vec<Migration> {
+        vec![
+            Migration {
+                from: Version::new(0, 8, 0),
+                to: Version::new(0, 9, 0),
+                migrate: rename_rule_names,
+            },
+            Migration {
+                from: Version::new(0, 9, 0),
+                to: CURRENT_CONFIG_VERSION,
+                migrate: |_| Ok(()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_migrate_runs_chain_passes() {
+        let yaml = r#"
+version: 0.8.0
+rules:
+  - title: Empty files
+    locations:
+      - !default_settings /tmp
+    filter_groups:
+      - filters:
+          - !empty
+        results: include
+        match: all
+"#;
+        let mut config = raw_config(yaml, ConfigFileFormat::Yaml).unwrap();
+        let migrations = test_migrations();
+
+        ConfigMigrator::new(&migrations)
+            .migrate(&mut config, &Version::new(0, 8, 0))
+            .unwrap();
+
+        assert_eq!(config["version"], CURRENT_CONFIG_VERSION.to_string());
+        assert_eq!(
+            config["rules"][0]["locations"][0]["default_settings"],
+            "/tmp"
+        );
+        let config: OrganizeConfig = serde_json::from_value(config).unwrap();
+        assert_eq!(config.rules()[0].name(), "Empty files");
+    }
+
+    #[test]
+    fn test_migrate_without_chain_fails() {
+        let mut config = Value::Object(Map::new());
+        let migrations = test_migrations();
+
+        let error = ConfigMigrator::new(&migrations)
+            .migrate(&mut config, &Version::new(0, 7, 0))
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            error,
+            OrganizeErrorKind::Config(ConfigErrorKind::MigrationNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_migrate_file_with_backup_passes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let original = r#"{"version": "0.9.0", "rules": [{"name": "Old"}]}"#;
+        std::fs::write(&path, original).unwrap();
+        let migrations = test_migrations();
+
+        let migrated = ConfigMigrator::new(&migrations)
+            .migrate_file(&path)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(migrated.from, Version::new(0, 9, 0));
+        assert_eq!(std::fs::read_to_string(migrated.backup).unwrap(), original);
+        assert_eq!(
+            OrganizeConfig::version_of_file(&path).unwrap(),
+            CURRENT_CONFIG_VERSION
+        );
+        assert_eq!(
+            OrganizeConfig::load_from_file(&path).unwrap().rules()[0].name(),
+            "Old"
+        );
+    }
+
+    #[test]
+    fn test_migrate_current_config_file_is_noop_passes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "version: 1.0.0\nrules: []\n").unwrap();
+
+        let migrated = ConfigMigrator::default().migrate_file(&path).unwrap();
+
+        assert!(migrated.is_none());
+        assert!(!dir.path().join("config.yaml.bak").exists());
+    }
+
+    #[test]
+    fn test_migrate_newer_config_file_fails() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        let original = "version: 99.0.0\nrules: []\n";
+        std::fs::write(&path, original).unwrap();
+
+        let error = ConfigMigrator::default()
+            .migrate_file(&path)
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            error,
+            OrganizeErrorKind::Config(ConfigErrorKind::UnsupportedConfigVersion { .. })
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert!(!dir.path().join("config.yaml.bak").exists());
+    }
+
+    #[test]
+    fn test_migrate_file_keeps_existing_backup_passes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let original = r#"{"version": "0.9.0", "rules": [{"name": "Old"}]}"#;
+        std::fs::write(&path, original).unwrap();
+        let existing = dir.path().join("config.json.bak");
+        std::fs::write(&existing, "previous backup").unwrap();
+        let migrations = test_migrations();
+
+        let migrated = ConfigMigrator::new(&migrations)
+            .migrate_file(&path)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(migrated.backup, dir.path().join("config.json.1.bak"));
+        assert_eq!(std::fs::read_to_string(migrated.backup).unwrap(), original);
+        assert_eq!(
+            std::fs::read_to_string(existing).unwrap(),
+            "previous backup"
+        );
+    }
+}
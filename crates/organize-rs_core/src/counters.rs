@@ -0,0 +1,393 @@
+//! Counters for numbering the items of a run with `{utility.counter}`
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{conflicts::exists, template_context, ActionKind},
+    actors::location_walker::{DirEntryData, EntryTemplateValues},
+    filesystem::is_same_item,
+    templating::{find_templates, TemplateFeatureKind, TemplateKind, UtilityKind},
+};
+
+/// Key of the counter in the template values of an item
+pub const COUNTER_KEY: &str = "utility.counter";
+
+/// Maximum number of counter values tried for an item, before the
+/// item is numbered regardless of collisions
+const MAX_COUNTER_ATTEMPTS: usize = 10_000;
+
+/// Order in which the items of a run are numbered
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Deserialize,
+    Serialize,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum CounterOrderKind {
+    /// by file name
+    #[default]
+    #[serde(rename = "name")]
+    Name,
+    /// by last modification time, oldest first
+    #[serde(rename = "last_modified")]
+    LastModified,
+    /// by size, smallest first
+    #[serde(rename = "size")]
+    Size,
+}
+
+/// Scope in which a counter runs
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Deserialize,
+    Serialize,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum CounterScopeKind {
+    /// one counter for all items of the rule
+    #[default]
+    #[serde(rename = "rule")]
+    Rule,
+    /// one counter for each destination directory
+    #[serde(rename = "destination")]
+    Destination,
+}
+
+/// How `{utility.counter}` numbers the items of a rule
+///
+/// ```yaml
+/// counter:
+///   start: 1
+///   step: 1
+///   padding: 3
+///   order_by: name
+///   scope: destination
+/// ```
+///
+/// Each action of a rule numbers the items on its own. Numbers whose
+/// destination already exists or was taken by another item of the run are
+/// skipped, so the numbering doesn't collide with existing items.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CounterSettings {
+    /// first number
+    pub start: u64,
+    /// difference between two consecutive numbers
+    pub step: u64,
+    /// minimal number of digits, numbers are padded with zeroes
+    pub padding: usize,
+    /// order in which the items are numbered
+    pub order_by: CounterOrderKind,
+    /// scope in which the counter runs
+    pub scope: CounterScopeKind,
+}
+
+impl Default for CounterSettings {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            step: 1,
+            padding: 0,
+            order_by: CounterOrderKind::default(),
+            scope: CounterScopeKind::default(),
+        }
+    }
+}
+
+impl CounterSettings {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Formats `number` with the configured padding
+    pub fn format(&self, number: u64) -> String {
+        format!("{number:0width$}", width = self.padding)
+    }
+
+    /// Numbers the items of `data` the `action` is applied to and returns
+    /// their template values extended by their number
+    ///
+    /// If the action doesn't refer to `{utility.counter}`, the template
+    /// values are returned unchanged. Items whose destination can't be
+    /// rendered are numbered without checking for collisions, applying
+    /// the action to them reports the error.
+    pub fn number_items(&self, action: &ActionKind, data: &DirEntryData) -> EntryTemplateValues {
+        let mut template_values = data.template_values().clone();
+        if !uses_counter(action) {
+            return template_values;
+        }
+
+        let paths = data.iter().map(|entry| entry.path()).collect::<Vec<_>>();
+        let mut next_numbers: HashMap<Option<PathBuf>, u64> = HashMap::new();
+        let mut claimed = HashSet::new();
+
+        for path in self.sorted(paths) {
+            let scope = match self.scope {
+                CounterScopeKind::Rule => None,
+                CounterScopeKind::Destination => self
+                    .destination(action, &template_values, &path, self.start)
+                    .and_then(|destination| destination.parent().map(Path::to_path_buf)),
+            };
+            let next = next_numbers.entry(scope).or_insert(self.start);
+
+            let mut number = *next;
+            for _ in 0..MAX_COUNTER_ATTEMPTS {
+                number = *next;
+                *next = next.saturating_add(self.step);
+
+                let Some(destination) = self.destination(action, &template_values, &path, number)
+                else {
+                    break;
+                };
+                let is_free = !exists(&destination) || is_same_item(&path, &destination);
+                if is_free && claimed.insert(destination) {
+                    break;
+                }
+            }
+
+            _ = template_values
+                .entry(path)
+                .or_default()
+                .insert(COUNTER_KEY.to_string(), self.format(number));
+        }
+
+        template_values
+    }
+
+    /// The destination of the item at `path`, if it is numbered with `number`
+    fn destination(
+        &self,
+        action: &ActionKind,
+        template_values: &EntryTemplateValues,
+        path: &Path,
+        number: u64,
+    ) -> Option<PathBuf> {
+        let mut context = template_context(template_values, path);
+        context.insert_value(COUNTER_KEY, self.format(number));
+        action.destination(path, &context).ok().flatten()
+    }
+
+    /// Sorts `paths` in the configured order, ties are broken by the path
+    fn sorted(&self, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut keyed = paths
+            .into_iter()
+            .map(|path| (self.sort_key(&path), path))
+            .collect::<Vec<_>>();
+
+        keyed.sort_by(|(key, path), (other_key, other_path)| {
+            key.cmp(other_key).then_with(|| path.cmp(other_path))
+        });
+
+        keyed.into_iter().map(|(_, path)| path).collect()
+    }
+
+    fn sort_key(&self, path: &Path) -> SortKey {
+        match self.order_by {
+            CounterOrderKind::Name => {
+                SortKey::Name(path.file_name().unwrap_or_default().to_os_string())
+            }
+            CounterOrderKind::LastModified => SortKey::Number(
+                path.metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |duration| duration.as_nanos()),
+            ),
+            CounterOrderKind::Size => SortKey::Number(
+                path.metadata()
+                    .map_or(0, |metadata| u128::from(metadata.len())),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Name(std::ffi::OsString),
+    Number(u128),
+}
+
+/// Returns `true` if any template of the action refers to `{utility.counter}`
+fn uses_counter(action: &ActionKind) -> bool {
+    let texts = match action {
+        ActionKind::Move { dst, .. } | ActionKind::Copy { dst, .. } => {
+            vec![dst.to_string_lossy().to_string()]
+        }
+        ActionKind::Rename { name, .. } => vec![name.clone()],
+        ActionKind::Echo { msg } => vec![msg.clone()],
+        ActionKind::Write { txt, file, .. } => {
+            vec![txt.clone(), file.to_string_lossy().to_string()]
+        }
+        _ => vec![],
+    };
+
+    texts.iter().any(|text| {
+        find_templates(text).is_ok_and(|templates| {
+            templates.iter().any(|template| {
+                TemplateKind::from_str(template).is_ok_and(|kind| {
+                    kind.data() == Some(&TemplateFeatureKind::Utility(UtilityKind::Counter))
+                })
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use jwalk::WalkDir;
+    use rstest::rstest;
+    use tempfile::{tempdir, TempDir};
+
+    use crate::actions::conflicts::ConflictResolutionKind;
+
+    use super::*;
+
+    /// creates the files with the given names and contents in a temporary directory
+    fn setup(files: &[(&str, &str)]) -> (TempDir, DirEntryData) {
+        let dir = tempdir().unwrap();
+        let entries = files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                write(&path, content).unwrap();
+                WalkDir::new(path).into_iter().next().unwrap().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        (dir, DirEntryData::from(entries))
+    }
+
+    fn rename(name: &str) -> ActionKind {
+        ActionKind::Rename {
+            name: name.to_string(),
+            on_conflict: ConflictResolutionKind::Skip,
+            rename_template: None,
+        }
+    }
+
+    fn numbers(dir: &TempDir, values: &EntryTemplateValues, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| values[&dir.path().join(name)][COUNTER_KEY].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_number_items_by_name_skips_existing_passes() {
+        let (dir, data) = setup(&[("c.jpg", ""), ("a.jpg", ""), ("b.jpg", "")]);
+        write(dir.path().join("holiday_002.jpg"), "existing").unwrap();
+        let settings = CounterSettings {
+            padding: 3,
+            ..Default::default()
+        };
+
+        let values = settings.number_items(
+            &rename("holiday_{utility.counter}.{metadata.extension}"),
+            &data,
+        );
+
+        assert_eq!(
+            numbers(&dir, &values, &["a.jpg", "b.jpg", "c.jpg"]),
+            ["001", "003", "004"]
+        );
+    }
+
+    #[rstest]
+    #[case(CounterOrderKind::Name, ["10", "15", "20"])]
+    #[case(CounterOrderKind::Size, ["20", "15", "10"])]
+    fn test_number_items_with_start_and_step_passes(
+        #[case] order_by: CounterOrderKind,
+        #[case] expected: [&str; 3],
+    ) {
+        let (dir, data) = setup(&[("a.txt", "aaa"), ("b.txt", "bb"), ("c.txt", "c")]);
+        let settings = CounterSettings {
+            start: 10,
+            step: 5,
+            order_by,
+            ..Default::default()
+        };
+
+        let values = settings.number_items(&rename("{utility.counter}.txt"), &data);
+
+        assert_eq!(
+            numbers(&dir, &values, &["a.txt", "b.txt", "c.txt"]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_number_items_per_destination_passes() {
+        let (dir, data) = setup(&[("a.jpg", ""), ("b.png", ""), ("c.jpg", "")]);
+        create_dir_all(dir.path().join("png")).unwrap();
+        write(dir.path().join("png").join("image_1.png"), "existing").unwrap();
+        let action = ActionKind::Move {
+            dst: dir
+                .path()
+                .join("{metadata.extension}")
+                .join("image_{utility.counter}.{metadata.extension}"),
+            on_conflict: ConflictResolutionKind::Skip,
+            rename_template: None,
+            filesystem: None,
+        };
+        let settings = CounterSettings {
+            scope: CounterScopeKind::Destination,
+            ..Default::default()
+        };
+
+        let values = settings.number_items(&action, &data);
+
+        assert_eq!(
+            numbers(&dir, &values, &["a.jpg", "b.png", "c.jpg"]),
+            ["1", "2", "2"]
+        );
+    }
+
+    #[test]
+    fn test_number_items_without_counter_keeps_values_passes() {
+        let (_dir, data) = setup(&[("a.txt", "")]);
+
+        let values = CounterSettings::default().number_items(&rename("{metadata.name}.md"), &data);
+
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_counter_settings_with_defaults_passes() {
+        let settings: CounterSettings =
+            serde_yaml::from_str("padding: 3\norder_by: last_modified").unwrap();
+
+        assert_eq!(
+            settings,
+            CounterSettings {
+                padding: 3,
+                order_by: CounterOrderKind::LastModified,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -1,9 +1,12 @@
 //! errors
 
 use displaydoc::Display;
+use semver::Version;
 use std::{error::Error as StdError, path::PathBuf};
 use thiserror::Error as ThisError;
 
+use crate::{aliases::ItemKind, config::ConfigFileFormat};
+
 /// Result type often returned from methods that can have organize `Error`s.
 pub type OrganizeResult<T> = std::result::Result<T, OrganizeError>;
 
@@ -34,6 +37,9 @@ pub enum OrganizeErrorKind {
     /// [`ConfigErrorKind`] describes the errors that can happen while dealing with config functions/closures
     #[error(transparent)]
     Config(#[from] ConfigErrorKind),
+    /// [`TemplateErrorKind`] describes the errors that can happen while rendering templates
+    #[error(transparent)]
+    Template(#[from] TemplateErrorKind),
     /// [`std::io::Error`]
     #[error(transparent)]
     StdIo(#[from] std::io::Error),
@@ -53,6 +59,18 @@ pub enum FilterErrorKind {
     ExtensionNotExtractable(std::path::PathBuf),
     /// discovered and inverted item: {0}
     InvertedItem(String),
+    /// script filter needs either a `source` or a `path`
+    ScriptMissing,
+    /// script filter has both a `source` and a `path`, only one of them can be used
+    ScriptAmbiguous,
+    /// failed to read script {0:?}: {1}
+    ReadingScriptFailed(PathBuf, std::io::Error),
+    /// script doesn't compile: {0}
+    ScriptCompilationFailed(String),
+    /// script failed for {path:?}: {message}
+    ScriptEvaluationFailed { path: PathBuf, message: String },
+    /// invalid regular expression `{expr}`: {source}
+    InvalidRegex { expr: String, source: regex::Error },
 }
 
 /// [`ActionErrorKind`] describes the errors that can be returned for an action
@@ -60,6 +78,21 @@ pub enum FilterErrorKind {
 pub enum ActionErrorKind {
     /// failed to open file: {0}
     FailedToOpenFile(#[from] std::io::Error),
+    /// failed to get user input: {0}
+    UserInputFailed(std::io::Error),
+    /// action is not implemented (yet): {0}
+    NotImplemented(String),
+    /// item has no file name: {0:?}
+    NoFileName(PathBuf),
+    /// not a valid file name: {0}
+    InvalidFileName(String),
+    /// failed to write to {path:?}: {source}
+    WritingFileFailed {
+        /// path of the file
+        path: PathBuf,
+        /// the underlying error
+        source: std::io::Error,
+    },
 }
 
 /// [`ConfigErrorKind`] describes the errors that can be returned for configs
@@ -70,7 +103,10 @@ pub enum ConfigErrorKind {
     YamlError(#[from] serde_yaml::Error),
     /// {0}
     #[error(transparent)]
-    RonError(#[from] ron::Error),
+    RonSerializeError(#[from] ron::Error),
+    /// {0}
+    #[error(transparent)]
+    RonDeserializeError(#[from] ron::error::SpannedError),
     /// {0}
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
@@ -82,8 +118,114 @@ pub enum ConfigErrorKind {
     TomlDeserializeError(#[from] toml::de::Error),
     /// config file format is not supported: {0}
     ConfigFileFormatNotSupported(String),
+    /// config file has no extension to derive its format from: {0:?}
+    ConfigFileExtensionMissing(PathBuf),
     /// config file already exists: {0}
     ConfigFileAlreadyExists(PathBuf),
+    /// failed to read config file {path:?}: {source}
+    ReadingConfigFileFailed {
+        /// path of the config file
+        path: PathBuf,
+        /// the underlying error
+        source: std::io::Error,
+    },
+    /// invalid config file {path:?}: {source}
+    InvalidConfigFile {
+        /// path of the config file
+        path: PathBuf,
+        /// line the error occurred in, starting at 1
+        line: Option<usize>,
+        /// column the error occurred in, starting at 1
+        column: Option<usize>,
+        /// the key (field or variant) that couldn't be deserialized
+        key: Option<String>,
+        /// the format-specific error
+        source: Box<ConfigErrorKind>,
+    },
+    /// failed to load config files:{0}
+    LoadingConfigsFailed(ConfigErrors),
+    /// config version {version} is newer than the supported version {supported}, please update organize
+    UnsupportedConfigVersion {
+        /// version of the config
+        version: Version,
+        /// newest config version this version of organize supports
+        supported: Version,
+    },
+    /// no migration of configs with version {0} available
+    MigrationNotFound(Version),
+    /// migration of the config from version {from} to {to} failed: {reason}
+    MigrationFailed {
+        /// version the migration upgrades from
+        from: Version,
+        /// version the migration upgrades to
+        to: Version,
+        /// why the migration failed
+        reason: String,
+    },
+    /// migration of {0} configs is not supported, please convert the config to another format first
+    MigrationNotSupported(ConfigFileFormat),
+    /// home directory to expand `~` in `{0}` couldn't be determined
+    HomeDirNotFound(String),
+    /// environment variable `{name}` in `{path}` is not set
+    EnvVarNotDefined {
+        /// name of the variable
+        name: String,
+        /// the path containing the variable
+        path: String,
+    },
+    /// alias `{0}` is not defined
+    AliasNotDefined(String),
+    /// alias `{name}` can't be used here, a {expected} alias is expected
+    AliasKindMismatch {
+        /// name of the alias
+        name: String,
+        /// kind of alias that can be used here
+        expected: ItemKind,
+    },
+    /// destination `{destination}` uses an alias with {count} folders, but only a single folder can be a destination
+    AmbiguousAliasDestination {
+        /// the destination containing the alias
+        destination: String,
+        /// number of folders the alias contains
+        count: usize,
+    },
+}
+
+/// Errors collected while loading multiple config files
+#[derive(Debug, Default)]
+pub struct ConfigErrors(pub Vec<OrganizeError>);
+
+impl std::fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for error in &self.0 {
+            write!(f, "\n  {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// [`TemplateErrorKind`] describes the errors that can be returned while rendering templates
+#[derive(ThisError, Debug, Display)]
+pub enum TemplateErrorKind {
+    /// not a valid template: {0}
+    InvalidTemplate(String),
+    /// template is missing a closing brace: {0}
+    UnclosedTemplate(String),
+    /// template is not supported (yet): {0}
+    NotSupported(String),
+    /// template refers to an unknown key: {0}
+    UnknownKey(String),
+    /// not a valid strftime format: {0}
+    InvalidFormat(String),
+    /// value for template `{template}` is not available for: {path:?}
+    ValueNotAvailable {
+        /// the template that couldn't be rendered
+        template: String,
+        /// the item the template was rendered for
+        path: PathBuf,
+    },
+    /// no free name found for {0:?} within the given number of attempts
+    NoFreeNameFound(PathBuf),
 }
 
 trait ErrorMarker: StdError {}
@@ -92,6 +234,7 @@ impl ErrorMarker for WalkerErrorKind {}
 impl ErrorMarker for FilterErrorKind {}
 impl ErrorMarker for ActionErrorKind {}
 impl ErrorMarker for ConfigErrorKind {}
+impl ErrorMarker for TemplateErrorKind {}
 impl ErrorMarker for std::io::Error {}
 
 impl<E> From<E> for OrganizeError
@@ -4,10 +4,15 @@
 // use std::os::unix::fs::symlink;
 
 use std::{
-    fs::{remove_dir, remove_file, rename},
-    path::Path,
+    fs::{create_dir_all, remove_dir, remove_dir_all, remove_file, rename},
+    path::{Path, PathBuf},
 };
 
+use directories::{BaseDirs, UserDirs};
+use filetime::{set_file_times, FileTime};
+
+use crate::error::{ConfigErrorKind, OrganizeResult};
+
 fn already_exists<A>(dst: &A) -> std::io::Result<()>
 where
     A: AsRef<Path>,
@@ -86,34 +91,177 @@ where
     Ok(())
 }
 
-/// wrapper around rename_to for convenience and clarity
-pub(crate) fn move_to<A, D>(src: A, dst: D) -> std::io::Result<()>
+/// Returns `true` if both paths point to the same item, e.g. if they
+/// only differ in case on a case-insensitive filesystem
+pub(crate) fn is_same_item<A, D>(src: A, dst: D) -> bool
 where
     A: AsRef<Path>,
     D: AsRef<Path>,
 {
-    rename_to(src, dst)
+    match (src.as_ref().canonicalize(), dst.as_ref().canonicalize()) {
+        (Ok(src), Ok(dst)) => src == dst,
+        _ => false,
+    }
 }
 
-pub(crate) fn rename_to<A, D>(src: A, dst: D) -> std::io::Result<()>
+/// Remove `src` and, if it is a directory, everything inside of it
+pub(crate) fn remove_recursively<A>(src: A) -> std::io::Result<()>
 where
     A: AsRef<Path>,
+{
+    if src.as_ref().symlink_metadata()?.is_dir() {
+        remove_dir_all(src)
+    } else {
+        remove_file(src)
+    }
+}
+
+/// Create all missing parent directories of `dst`
+pub(crate) fn create_parent_dirs<D>(dst: D) -> std::io::Result<()>
+where
     D: AsRef<Path>,
 {
-    // TODO: Conflict handling here
-    already_exists(&dst)?;
+    match dst.as_ref().parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
 
-    let src_type = src.as_ref().metadata()?.file_type();
+/// Expands a leading `~` and the environment variables in `path`
+///
+/// Variables are written as `$VAR` or `${VAR}`. If they aren't set,
+/// `$HOME` and the `$XDG_*` base and user directories fall back to the
+/// defaults of the platform. Templates, e.g. `{metadata.name}`, are kept
+/// as they are.
+///
+/// # Errors
+///
+/// If the home directory can't be determined or a variable is not set
+pub fn expand_path(path: &Path) -> OrganizeResult<PathBuf> {
+    let Some(text) = path.to_str() else {
+        // variables and `~` are valid UTF-8, so there is nothing to expand
+        return Ok(path.to_path_buf());
+    };
 
-    if src_type.is_file() {
-        rename(src, dst)?;
-    } else {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("can not move/rename: {}", src.as_ref().display()),
-        ));
+    expand_path_with(text, |name| {
+        std::env::var(name).ok().or_else(|| default_dir(name))
+    })
+}
+
+fn expand_path_with(
+    path: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> OrganizeResult<PathBuf> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+
+    if let Some(after_tilde) = path.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(std::path::is_separator) {
+            let home =
+                lookup("HOME").ok_or_else(|| ConfigErrorKind::HomeDirNotFound(path.to_string()))?;
+            expanded.push_str(&home);
+            rest = after_tilde;
+        }
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after_dollar = &rest[start + 1..];
+
+        let (name, after_variable) = match after_dollar.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after_dollar),
+            },
+            None => {
+                let end = after_dollar
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after_dollar.len());
+                (&after_dollar[..end], &after_dollar[end..])
+            }
+        };
+
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            // not a variable, e.g. a `$` in a file name
+            expanded.push('$');
+            rest = after_dollar;
+            continue;
+        }
+
+        let value = lookup(name).ok_or_else(|| ConfigErrorKind::EnvVarNotDefined {
+            name: name.to_string(),
+            path: path.to_string(),
+        })?;
+        expanded.push_str(&value);
+        rest = after_variable;
+    }
+
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// The platform default of `$HOME` and the `$XDG_*` directories
+fn default_dir(name: &str) -> Option<String> {
+    let dir = match name {
+        "HOME" => BaseDirs::new()?.home_dir().to_path_buf(),
+        "XDG_CONFIG_HOME" => BaseDirs::new()?.config_dir().to_path_buf(),
+        "XDG_DATA_HOME" => BaseDirs::new()?.data_dir().to_path_buf(),
+        "XDG_CACHE_HOME" => BaseDirs::new()?.cache_dir().to_path_buf(),
+        "XDG_STATE_HOME" => BaseDirs::new()?.state_dir()?.to_path_buf(),
+        "XDG_RUNTIME_DIR" => BaseDirs::new()?.runtime_dir()?.to_path_buf(),
+        "XDG_DESKTOP_DIR" => UserDirs::new()?.desktop_dir()?.to_path_buf(),
+        "XDG_DOCUMENTS_DIR" => UserDirs::new()?.document_dir()?.to_path_buf(),
+        "XDG_DOWNLOAD_DIR" => UserDirs::new()?.download_dir()?.to_path_buf(),
+        "XDG_MUSIC_DIR" => UserDirs::new()?.audio_dir()?.to_path_buf(),
+        "XDG_PICTURES_DIR" => UserDirs::new()?.picture_dir()?.to_path_buf(),
+        "XDG_PUBLICSHARE_DIR" => UserDirs::new()?.public_dir()?.to_path_buf(),
+        "XDG_TEMPLATES_DIR" => UserDirs::new()?.template_dir()?.to_path_buf(),
+        "XDG_VIDEOS_DIR" => UserDirs::new()?.video_dir()?.to_path_buf(),
+        _ => return None,
+    };
+
+    Some(dir.to_string_lossy().to_string())
+}
+
+/// Windows error code for moving a file to a different disk drive
+#[cfg(windows)]
+const ERROR_NOT_SAME_DEVICE: i32 = 17;
+
+/// Returns `true` if the error was caused by renaming an item
+/// across filesystems/devices
+fn is_cross_device_error(err: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    let cross_device = Some(libc::EXDEV);
+    #[cfg(windows)]
+    let cross_device = Some(ERROR_NOT_SAME_DEVICE);
+    #[cfg(not(any(unix, windows)))]
+    let cross_device = None;
+
+    err.raw_os_error().is_some() && err.raw_os_error() == cross_device
+}
+
+/// Move the file or directory `src` to `dst`
+///
+/// Missing parent directories of `dst` are created. If `src` and `dst`
+/// are on different filesystems, `src` is copied to `dst` and removed
+/// afterwards.
+pub(crate) fn move_to<A, D>(src: A, dst: D) -> std::io::Result<()>
+where
+    A: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    if !is_same_item(&src, &dst) {
+        already_exists(&dst)?;
+    }
+    create_parent_dirs(&dst)?;
+
+    match rename(src.as_ref(), dst.as_ref()) {
+        Err(err) if is_cross_device_error(&err) => {
+            copy_to(src.as_ref(), dst.as_ref())?;
+            remove_recursively(src)
+        }
+        result => result,
     }
-    Ok(())
 }
 
 pub(crate) fn symlink_to<A, D>(src: A, dst: D) -> std::io::Result<()>
@@ -139,20 +287,30 @@ where
     Ok(())
 }
 
+/// Copy the file or directory `src` to `dst`
+///
+/// Missing parent directories of `dst` are created, permissions and
+/// modification times are preserved. Fails on the first item that can't
+/// be copied, use [`copy_dir_to`] to get the results for each file.
 pub(crate) fn copy_to<A, D>(src: A, dst: D) -> std::io::Result<()>
 where
     A: AsRef<Path>,
     D: AsRef<Path>,
 {
-    // TODO: Conflict handling here
     already_exists(&dst)?;
+    create_parent_dirs(&dst)?;
 
     let src_type = src.as_ref().metadata()?.file_type();
 
     if src_type.is_file() {
-        std::fs::copy(src, dst)?;
+        copy_file_to(src, dst)?;
     } else if src_type.is_dir() {
-        copy_dir_to(src, dst)?;
+        if let Some(err) = copy_dir_to(src, dst)?
+            .into_iter()
+            .find_map(|(_, result)| result.err())
+        {
+            return Err(err);
+        }
     } else {
         return Err(std::io::Error::new(
             std::io::ErrorKind::Other,
@@ -162,25 +320,73 @@ where
     Ok(())
 }
 
+/// Copy the file `src` to `dst` and preserve its permissions and timestamps
+fn copy_file_to<A, D>(src: A, dst: D) -> std::io::Result<()>
+where
+    A: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    // `std::fs::copy` already takes care of the permissions
+    std::fs::copy(src.as_ref(), dst.as_ref())?;
+    preserve_times(src, dst)
+}
+
+fn preserve_times<A, D>(src: A, dst: D) -> std::io::Result<()>
+where
+    A: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    let metadata = src.as_ref().metadata()?;
+    set_file_times(
+        dst,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+}
+
 /// Copy the existing directory `src` to the target path `dst`
-pub(crate) fn copy_dir_to<A, D>(src: A, dst: D) -> std::io::Result<()>
+///
+/// Files that fail to be copied don't stop the copying of the remaining
+/// items, instead the result for each file is returned.
+pub(crate) fn copy_dir_to<A, D>(
+    src: A,
+    dst: D,
+) -> std::io::Result<Vec<(PathBuf, std::io::Result<()>)>>
 where
     A: AsRef<Path>,
     D: AsRef<Path>,
 {
-    // TODO: Conflict handling here
     already_exists(&dst)?;
+    create_dir_all(dst.as_ref())?;
 
-    if !dst.as_ref().is_dir() {
-        std::fs::create_dir(dst.as_ref())?;
-    }
+    let mut results = vec![];
 
     for entry_result in src.as_ref().read_dir()? {
-        let entry = entry_result?;
-        copy_to(&entry.path(), &dst.as_ref().join(entry.file_name()))?;
+        let entry = match entry_result {
+            Ok(entry) => entry,
+            Err(err) => {
+                results.push((src.as_ref().to_path_buf(), Err(err)));
+                continue;
+            }
+        };
+        let target = dst.as_ref().join(entry.file_name());
+
+        if matches!(entry.file_type(), Ok(file_type) if file_type.is_dir()) {
+            match copy_dir_to(entry.path(), &target) {
+                Ok(mut dir_results) => results.append(&mut dir_results),
+                Err(err) => results.push((entry.path(), Err(err))),
+            }
+        } else {
+            results.push((entry.path(), copy_file_to(entry.path(), &target)));
+        }
     }
 
-    Ok(())
+    // set after copying the contents, as that changes the timestamps and
+    // read-only directories can't be filled anymore
+    preserve_times(&src, &dst)?;
+    std::fs::set_permissions(dst, src.as_ref().metadata()?.permissions())?;
+
+    Ok(results)
 }
 
 /// Create a directory symlink to the given src with the given link name.
@@ -254,3 +460,51 @@ where
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::error::OrganizeErrorKind;
+
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/user".to_string()),
+            "XDG_DOWNLOAD_DIR" => Some("/home/user/Downloads".to_string()),
+            "PROJECT" => Some("organize".to_string()),
+            _ => None,
+        }
+    }
+
+    #[rstest]
+    #[case("~", "/home/user")]
+    #[case("~/Desktop/PDFs/", "/home/user/Desktop/PDFs/")]
+    #[case(
+        "$XDG_DOWNLOAD_DIR/{metadata.extension}",
+        "/home/user/Downloads/{metadata.extension}"
+    )]
+    #[case("/src/${PROJECT}_backup/$PROJECT", "/src/organize_backup/organize")]
+    #[case("~other/file", "~other/file")]
+    #[case("/costs/$5.txt", "/costs/$5.txt")]
+    #[case("/tmp/$", "/tmp/$")]
+    fn test_expand_path_passes(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(
+            expand_path_with(path, lookup).unwrap(),
+            PathBuf::from(expected)
+        );
+    }
+
+    #[test]
+    fn test_expand_path_with_undefined_variable_fails() {
+        let err = expand_path_with("$UNDEFINED/file", lookup)
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(
+            err,
+            OrganizeErrorKind::Config(ConfigErrorKind::EnvVarNotDefined { name, .. }) if name == "UNDEFINED"
+        ));
+    }
+}
@@ -1,10 +1,14 @@
 //! Filters that `organize` operates with
+mod content;
+mod duplicates;
+mod exif_data;
 mod impl_;
 mod impl_traits;
+pub(crate) mod script;
 #[cfg(test)]
 mod tests;
 
-use std::fmt::Debug;
+use std::{fmt::Debug, path::PathBuf};
 
 #[cfg(feature = "cli")]
 use clap::{Args, Subcommand, ValueEnum};
@@ -18,9 +22,15 @@ use serde_with::StringWithSeparator;
 
 use serde_with::serde_as;
 
-use crate::parsers::{period_range::PeriodRange, size_range::SizeRange};
+use crate::{
+    parsers::{period_range::PeriodRange, size_range::SizeRange},
+    templating::TemplateValues,
+};
 
 pub type FilterClosure<'a, C> = Box<dyn FnMut(&DirEntry<C>) -> bool + 'a>;
+/// Filter closure returning the template values extracted from a
+/// matching entry, or `None` if the entry doesn't match
+pub type ValueFilterClosure<'a, C> = Box<dyn FnMut(&DirEntry<C>) -> Option<TemplateValues> + 'a>;
 pub type FilterClosureCollection<'a, C> = Vec<FilterClosure<'a, C>>;
 pub type FilterFilterClosureSliceMut<'a, C> = &'a mut [Box<dyn FnMut(&DirEntry<C>) -> bool>];
 
@@ -53,13 +63,18 @@ pub enum FilterOperationKind<T> {
 }
 
 /// Should filter results be included or excluded
-#[derive(Debug, Clone, Deserialize, Serialize, Display, Copy)]
+///
+/// Entries matched by an `exclude` group are removed, even if an `include`
+/// group matches them as well, see [`FilterApplicator::get_applicable_items`].
+///
+/// [`FilterApplicator::get_applicable_items`]: crate::actors::filter_applicator::FilterApplicator::get_applicable_items
+#[derive(Debug, Clone, Deserialize, Serialize, Display, Copy, PartialEq, Eq)]
 pub enum FilterGroupOperationKind {
     /// Exclude
-    #[serde(rename = "include")]
+    #[serde(rename = "exclude")]
     Exclude,
     /// Include
-    #[serde(rename = "exclude")]
+    #[serde(rename = "include")]
     Include,
 }
 
@@ -340,9 +355,12 @@ pub enum FilterKind {
     /// duplicate. This works on all methods, for example "-first_seen",
     /// "-name", "-created", "-lastmodified".
     ///
+    /// Only the duplicates match, the originals don't. Duplicates are
+    /// searched among all items within the locations of a rule.
+    ///
     /// # Result
     ///
-    /// The path to the original
+    /// The path to the original, available as `{duplicate.original}`
     ///
     /// # Example
     ///
@@ -373,7 +391,7 @@ pub enum FilterKind {
     ///      actions:
     ///        - mode: preview
     ///          action: !echo
-    ///            msg: "{{entry.duplicate}} is a duplicate of {{entry.original}}"
+    ///            msg: "{metadata.name} is a duplicate of {duplicate.original}"
     ///      tags:
     ///        - !custom Test::Filter::Duplicate
     /// # "#;
@@ -423,22 +441,27 @@ pub enum FilterKind {
     /// Filter images by their EXIF data
     ///
     /// The exif filter can be used as a filter as well as a way to
-    /// get exif information into your actions
+    /// get exif information into your actions. JPEG, TIFF, HEIF, PNG
+    /// and WebP images are supported.
     ///
     /// # Result
     ///
     /// An object of all the collected exif inforamtion available in the file.
     /// Typically it consists of the following tags (if present in the file):
     ///
-    /// - `{exif.image}` -- information related to the main image
-    /// - `{exif.exif}` -- Exif information
-    /// - `{exif.gps}` -- GPS information
-    /// - `{exif.interoperability}` -- Interoperability information
+    /// - `{exif.image.<tag>}` -- information related to the main image
+    /// - `{exif.exif.<tag>}` -- Exif information
+    /// - `{exif.gps.<tag>}` -- GPS information
+    /// - `{exif.interoperability.<tag>}` -- Interoperability information
+    ///
+    /// Commonly used information is available in short as `{exif.make}`,
+    /// `{exif.model}`, `{exif.lens}`, `{exif.datetime}` (also with `.year`,
+    /// `.month` and `.day`), `{exif.gps.latitude}` and `{exif.gps.longitude}`.
     ///
     /// # Example
     ///
-    /// Copy all images which contain GPS information while keeping
-    /// subfolder structure
+    /// Copy all images which contain GPS information and sort them by
+    /// year and camera model
     ///
     /// ```rust
     /// # use organize_rs_core::config::{OrganizeConfig, ConfigFileFormat};
@@ -455,20 +478,26 @@ pub enum FilterKind {
     ///        - filters:
     ///            - !exif
     ///              contains:
-    ///                - gps.gpsdate
+    ///                - gps.gpslatitude
     ///          results: include
     ///          match: all
     ///      actions:
     ///        - mode: preview
     ///          action: !copy
-    ///            dst: ~/Pictures/with_gps/{{relative_path}}/
+    ///            dst: ~/Pictures/with_gps/{exif.datetime.year}/{exif.model}/
     ///      tags:
     ///        - !custom Test::Filter::ExifGps
     /// # "#;
     /// # let config = OrganizeConfig::load_from_string(rule, ConfigFileFormat::Yaml);
     /// ```
     #[serde(rename = "exif")]
-    Exif { contains: Vec<String> },
+    Exif {
+        /// The tags the image needs to contain, e.g. `gps.gpslatitude`,
+        /// optionally with the value they need to have, e.g. `model=Pixel 7`
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default)]
+        contains: Vec<String>,
+    },
     /// Match locations by their file extension
     ///
     /// # Result
@@ -515,12 +544,14 @@ pub enum FilterKind {
     },
     /// Match file content with the given regular expression
     ///
-    /// Any named groups `((?P<groupname>.*))` in your regular
+    /// The text of text files and PDFs is searched, other files don't
+    /// match. Any named groups `((?P<groupname>.*))` in your regular
     /// expression will be returned.
     ///
     /// # Result
     ///  
-    /// The text matched with the named group `(?P<groupname>)`
+    /// The text matched with the named group `(?P<groupname>)`, available
+    /// as `{content.groupname}`
     ///
     /// # Example
     ///
@@ -546,7 +577,7 @@ pub enum FilterKind {
     ///      actions:
     ///        - mode: preview
     ///          action: !move
-    ///            dst: ~/Documents/Invoices/{{file_content.customer}}/
+    ///            dst: ~/Documents/Invoices/{content.customer}/
     ///      tags:
     ///        - !custom Test::Filter::FileContent
     /// # "#;
@@ -998,6 +1029,59 @@ pub enum FilterKind {
         #[cfg_attr(feature = "cli", arg(long))]
         expr: String,
     },
+    /// Match locations with a [Rhai](https://rhai.rs) script
+    ///
+    /// The script is compiled once per rule and evaluated for each entry,
+    /// it needs to return a boolean. Either the script itself is given as
+    /// `source` or the path to a `*.rhai` file containing it.
+    ///
+    /// The entry is available read-only as `entry` with the properties `path`,
+    /// `name`, `extension`, `size` (in bytes), `created`, `last_modified`,
+    /// `last_accessed` (in seconds since the Unix epoch) and `mime`. `now()`
+    /// returns the current time in seconds since the Unix epoch.
+    ///
+    /// # Example
+    ///
+    /// Match PDFs bigger than 1 MiB, that haven't been modified for a week
+    ///
+    /// ```rust
+    /// # use organize_rs_core::config::{OrganizeConfig, ConfigFileFormat};
+    /// # let rule = r#"
+    /// rules:
+    ///    - name: Match big and old PDFs with a script
+    ///      enabled: true
+    ///      locations:
+    ///         - !non_recursive
+    ///           path: ~/Downloads
+    ///           target: files
+    ///      filter_groups:
+    ///        - filters:
+    ///           - !script
+    ///             source: |
+    ///               entry.extension == "pdf"
+    ///                 && entry.size > 1024 * 1024
+    ///                 && now() - entry.last_modified > 7 * 24 * 60 * 60
+    ///          results: include
+    ///          match: all
+    ///      actions:
+    ///        - mode: preview
+    ///          action: !trash
+    ///      tags:
+    ///        - !custom Test::Filter::Script
+    /// # "#;
+    /// # let config = OrganizeConfig::load_from_string(rule, ConfigFileFormat::Yaml);
+    /// ```
+    #[serde(rename = "script")]
+    Script {
+        /// The script to evaluate for each entry
+        #[cfg_attr(feature = "cli", arg(long, conflicts_with = "path"))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        /// Path to a `*.rhai` file containing the script
+        #[cfg_attr(feature = "cli", arg(long))]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
     /// Match files by their size
     ///
     /// Accepts file size conditions, e.g: "500MB..", "..20kb", "0KB..", "10KiB..".
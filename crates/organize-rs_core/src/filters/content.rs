@@ -0,0 +1,70 @@
+//! Extraction of the text content of files
+
+use std::{fs::read_to_string, path::Path};
+
+use mime::Mime;
+
+/// Extracts the text of the file at `path`
+///
+/// Text files are read as they are, the text of PDFs is extracted. Returns
+/// `None` for other (binary) files or if the text can't be extracted.
+pub(crate) fn extract_text(path: &Path) -> Option<String> {
+    if !path.is_file() {
+        return None;
+    }
+
+    let Some(kind) = infer::get_from_path(path).ok()? else {
+        // `infer` detects types by their magic numbers, which plain text lacks
+        return read_to_string(path).ok();
+    };
+
+    let mime_type = kind.mime_type().parse::<Mime>().ok()?;
+    if mime_type == mime::APPLICATION_PDF {
+        extract_pdf_text(path)
+    } else if mime_type.type_() == mime::TEXT {
+        read_to_string(path).ok()
+    } else {
+        None
+    }
+}
+
+fn extract_pdf_text(path: &Path) -> Option<String> {
+    // `pdf-extract` panics on some malformed documents
+    match std::panic::catch_unwind(|| pdf_extract::extract_text(path)) {
+        Ok(Ok(text)) => Some(text),
+        Ok(Err(err)) => {
+            eprintln!("couldn't extract text of {}: {err}", path.display());
+            None
+        }
+        Err(_) => {
+            eprintln!("couldn't extract text of {}", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("invoice.txt", Some("Customer Globex"))]
+    #[case("invoice.pdf", Some("Customer Acme"))]
+    #[case("photo.jpg", None)]
+    fn test_extract_text_passes(#[case] name: &str, #[case] contained: Option<&str>) {
+        let path: PathBuf = ["tests", "fixtures", "filters", "file_content", name]
+            .iter()
+            .collect();
+
+        let text = extract_text(&path);
+
+        match contained {
+            Some(contained) => assert!(text.unwrap().contains(contained)),
+            None => assert!(text.is_none()),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Detection of duplicated files
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    hash::Hash,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use itertools::Itertools;
+
+use crate::filters::DuplicateKind;
+
+/// Number of bytes hashed to tell apart files of the same size cheaply
+const PARTIAL_HASH_SIZE: u64 = 4096;
+
+/// A file that might be a duplicate
+#[derive(Debug)]
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    /// position in which the file has been visited
+    position: usize,
+}
+
+/// Finds duplicated files among a collection of items
+///
+/// Candidates are grouped by their size first, then by a hash of their
+/// first bytes and only the remaining ones are hashed completely, so most
+/// files never need to be read entirely.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DuplicateFinder {
+    detect_original_by: DuplicateKind,
+    reverse: bool,
+}
+
+impl DuplicateFinder {
+    pub fn new(detect_original_by: DuplicateKind, reverse: bool) -> Self {
+        Self {
+            detect_original_by,
+            reverse,
+        }
+    }
+
+    /// Returns each duplicate among `paths` together with the original
+    /// it duplicates
+    ///
+    /// `paths` need to be given in the order they have been visited in.
+    /// Directories and files that can't be read are never duplicates.
+    pub fn find_duplicates(
+        &self,
+        paths: impl IntoIterator<Item = PathBuf>,
+    ) -> HashMap<PathBuf, PathBuf> {
+        let candidates = paths
+            .into_iter()
+            .unique()
+            .enumerate()
+            .filter_map(|(position, path)| {
+                let metadata = path.metadata().ok()?;
+                metadata.is_file().then_some(Candidate {
+                    path,
+                    size: metadata.len(),
+                    position,
+                })
+            })
+            .collect_vec();
+
+        let same_size = regroup(vec![candidates], |candidate| Some(candidate.size));
+        let same_start = regroup(same_size, |candidate| {
+            hash_file(&candidate.path, Some(PARTIAL_HASH_SIZE))
+        });
+        // the partial hash already covered small files completely
+        let (small, large): (Vec<_>, Vec<_>) = same_start
+            .into_iter()
+            .partition(|group| group[0].size <= PARTIAL_HASH_SIZE);
+        let same_content = regroup(large, |candidate| hash_file(&candidate.path, None));
+
+        small
+            .into_iter()
+            .chain(same_content)
+            .flat_map(|mut group| {
+                self.sort_by_originality(&mut group);
+                let mut group = group.into_iter();
+                let original = group.next().map(|candidate| candidate.path);
+                group.filter_map(move |duplicate| Some((duplicate.path, original.clone()?)))
+            })
+            .collect()
+    }
+
+    /// Sorts the group, so the original comes first
+    fn sort_by_originality(&self, group: &mut [Candidate]) {
+        match self.detect_original_by {
+            // all duplicates share their hash, the config validation rejects
+            // `Hash` for that reason, so keep the visiting order
+            DuplicateKind::FirstSeen | DuplicateKind::Hash => {
+                group.sort_by_key(|candidate| candidate.position);
+            }
+            DuplicateKind::Name => group.sort_by(|left, right| {
+                left.path
+                    .file_name()
+                    .cmp(&right.path.file_name())
+                    .then_with(|| left.path.cmp(&right.path))
+            }),
+            DuplicateKind::Created => {
+                group.sort_by_cached_key(|candidate| (created(&candidate.path), candidate.position))
+            }
+            DuplicateKind::LastModified => group.sort_by_cached_key(|candidate| {
+                (last_modified(&candidate.path), candidate.position)
+            }),
+        }
+
+        if self.reverse {
+            group.reverse();
+        }
+    }
+}
+
+/// Splits the groups further by `key`, candidates without a key and
+/// groups left with a single candidate are dropped
+fn regroup<K, F>(groups: Vec<Vec<Candidate>>, key: F) -> Vec<Vec<Candidate>>
+where
+    K: Eq + Hash,
+    F: Fn(&Candidate) -> Option<K>,
+{
+    groups
+        .into_iter()
+        .flat_map(|group| {
+            group
+                .into_iter()
+                .filter_map(|candidate| key(&candidate).map(|key| (key, candidate)))
+                .into_group_map()
+                .into_values()
+                .filter(|group| group.len() > 1)
+        })
+        .collect()
+}
+
+/// Hashes the file at `path`, only its first `limit` bytes if given
+fn hash_file(path: &Path, limit: Option<u64>) -> Option<blake3::Hash> {
+    let hash = |mut reader: Box<dyn Read>| -> io::Result<blake3::Hash> {
+        let mut hasher = blake3::Hasher::new();
+        _ = io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize())
+    };
+
+    let file = File::open(path).ok()?;
+    let result = match limit {
+        Some(limit) => hash(Box::new(file.take(limit))),
+        None => hash(Box::new(file)),
+    };
+
+    result
+        .map_err(|err| eprintln!("couldn't hash {}: {err}", path.display()))
+        .ok()
+}
+
+fn created(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|metadata| metadata.created()).ok()
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use filetime::{set_file_mtime, FileTime};
+    use rstest::rstest;
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    /// creates `b.txt` and `c.txt` with the same content, `a.txt` of the same
+    /// size, but another content, and a bigger copy in `big_a.bin`/`big_b.bin`
+    fn setup() -> (TempDir, Vec<PathBuf>) {
+        let dir = tempdir().unwrap();
+        let big = vec![7u8; (PARTIAL_HASH_SIZE * 2) as usize];
+        let mut big_other = big.clone();
+        *big_other.last_mut().unwrap() = 8;
+
+        let files: [(&str, &[u8]); 5] = [
+            ("c.txt", b"content"),
+            ("a.txt", b"CONTENT"),
+            ("b.txt", b"content"),
+            ("big_a.bin", &big),
+            ("big_b.bin", &big_other),
+        ];
+        let paths = files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                write(&path, content).unwrap();
+                path
+            })
+            .collect_vec();
+
+        (dir, paths)
+    }
+
+    #[rstest]
+    #[case(DuplicateKind::FirstSeen, false, "b.txt", "c.txt")]
+    #[case(DuplicateKind::FirstSeen, true, "c.txt", "b.txt")]
+    #[case(DuplicateKind::Name, false, "c.txt", "b.txt")]
+    #[case(DuplicateKind::Name, true, "b.txt", "c.txt")]
+    fn test_find_duplicates_passes(
+        #[case] detect_original_by: DuplicateKind,
+        #[case] reverse: bool,
+        #[case] duplicate: &str,
+        #[case] original: &str,
+    ) {
+        let (dir, paths) = setup();
+
+        let duplicates = DuplicateFinder::new(detect_original_by, reverse).find_duplicates(paths);
+
+        assert_eq!(
+            duplicates,
+            HashMap::from([(dir.path().join(duplicate), dir.path().join(original))])
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates_by_last_modified_passes() {
+        let (dir, paths) = setup();
+        set_file_mtime(
+            dir.path().join("c.txt"),
+            FileTime::from_unix_time(1_600_000_000, 0),
+        )
+        .unwrap();
+        set_file_mtime(
+            dir.path().join("b.txt"),
+            FileTime::from_unix_time(1_500_000_000, 0),
+        )
+        .unwrap();
+
+        let duplicates =
+            DuplicateFinder::new(DuplicateKind::LastModified, false).find_duplicates(paths);
+
+        assert_eq!(
+            duplicates.get(&dir.path().join("c.txt")),
+            Some(&dir.path().join("b.txt"))
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates_of_big_files_passes() {
+        let (dir, mut paths) = setup();
+        let big_copy = dir.path().join("big_copy.bin");
+        std::fs::copy(dir.path().join("big_b.bin"), &big_copy).unwrap();
+        paths.push(big_copy.clone());
+
+        let duplicates =
+            DuplicateFinder::new(DuplicateKind::FirstSeen, false).find_duplicates(paths);
+
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(
+            duplicates.get(&big_copy),
+            Some(&dir.path().join("big_b.bin"))
+        );
+    }
+}
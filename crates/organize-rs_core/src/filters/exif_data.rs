@@ -0,0 +1,193 @@
+//! Reading of the EXIF metadata of images
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use exif::{Context, DateTime, Exif, Field, In, Reader, Tag, Value};
+use itertools::Itertools;
+
+use crate::templating::TemplateValues;
+
+/// Reads the EXIF metadata of the image at `path` as template values
+///
+/// JPEG, TIFF, HEIF, PNG and WebP images are supported. Each field of
+/// the primary image is available as `exif.<group>.<tag>`, where group is
+/// one of `image`, `exif`, `gps` or `interoperability`, e.g.
+/// `exif.image.model`. Commonly used fields are additionally available as
+/// `exif.make`, `exif.model`, `exif.lens`, `exif.datetime` (also with
+/// `.year`, `.month` and `.day`) and `exif.gps.latitude`/`exif.gps.longitude`.
+///
+/// Returns `None` if the file contains no EXIF metadata.
+pub(crate) fn read_exif(path: &Path) -> Option<TemplateValues> {
+    let file = File::open(path).ok()?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let mut values: TemplateValues = exif
+        .fields()
+        .filter(|field| field.ifd_num == In::PRIMARY)
+        .filter_map(|field| {
+            let group = group_name(field.tag.context())?;
+            let tag = field.tag.to_string().to_lowercase();
+            Some((format!("exif.{group}.{tag}"), field_value(field)))
+        })
+        .collect();
+
+    let mut insert = |key: &str, value: Option<String>| {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            _ = values.insert(format!("exif.{key}"), value);
+        }
+    };
+
+    insert("make", primary_value(&exif, Tag::Make));
+    insert("model", primary_value(&exif, Tag::Model));
+    insert("lens", primary_value(&exif, Tag::LensModel));
+    insert(
+        "gps.latitude",
+        coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef),
+    );
+    insert(
+        "gps.longitude",
+        coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef),
+    );
+
+    if let Some(date) = capture_date(&exif) {
+        insert(
+            "datetime",
+            Some(format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                date.year, date.month, date.day, date.hour, date.minute, date.second
+            )),
+        );
+        insert("datetime.year", Some(format!("{:04}", date.year)));
+        insert("datetime.month", Some(format!("{:02}", date.month)));
+        insert("datetime.day", Some(format!("{:02}", date.day)));
+    }
+
+    Some(values)
+}
+
+/// Returns `true` if the EXIF `values` fulfill `condition`
+///
+/// The condition is either the name of a tag that needs to exist, e.g.
+/// `gps.gpslatitude`, or a tag and the value it needs to have, e.g.
+/// `model=Pixel 7`. Tags and values are compared case-insensitively.
+pub(crate) fn fulfills_condition(values: &TemplateValues, condition: &str) -> bool {
+    let (tag, expected) = match condition.split_once('=') {
+        Some((tag, value)) => (tag, Some(value.trim())),
+        None => (condition, None),
+    };
+    let tag = tag.trim().to_lowercase();
+    let key = if tag.starts_with("exif.") {
+        tag
+    } else {
+        format!("exif.{tag}")
+    };
+
+    match (values.get(&key), expected) {
+        (Some(value), Some(expected)) => value.eq_ignore_ascii_case(expected),
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+fn group_name(context: Context) -> Option<&'static str> {
+    match context {
+        Context::Tiff => Some("image"),
+        Context::Exif => Some("exif"),
+        Context::Gps => Some("gps"),
+        Context::Interop => Some("interoperability"),
+        _ => None,
+    }
+}
+
+fn field_value(field: &Field) -> String {
+    match &field.value {
+        Value::Ascii(strings) => strings
+            .iter()
+            .map(|string| String::from_utf8_lossy(string).trim().to_string())
+            .join(" "),
+        _ => field.display_value().to_string(),
+    }
+}
+
+fn primary_value(exif: &Exif, tag: Tag) -> Option<String> {
+    exif.get_field(tag, In::PRIMARY).map(field_value)
+}
+
+/// The date the image was taken, falling back to the date it was last changed
+fn capture_date(exif: &Exif) -> Option<DateTime> {
+    [Tag::DateTimeOriginal, Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(strings) => DateTime::from_ascii(strings.first()?).ok(),
+            _ => None,
+        })
+}
+
+/// A GPS coordinate in decimal degrees, negative in the south and west
+fn coordinate(exif: &Exif, tag: Tag, reference_tag: Tag) -> Option<String> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = parts.as_slice() else {
+        return None;
+    };
+
+    let decimal = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    let negative = matches!(
+        primary_value(exif, reference_tag).as_deref(),
+        Some("S" | "W")
+    );
+
+    Some(format!("{:.6}", if negative { -decimal } else { decimal }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    fn get_fixture(name: &str) -> PathBuf {
+        ["tests", "fixtures", "filters", "exif", name]
+            .iter()
+            .collect()
+    }
+
+    #[rstest]
+    #[case("exif.make", "Organize")]
+    #[case("exif.model", "Test Camera 1")]
+    #[case("exif.image.model", "Test Camera 1")]
+    #[case("exif.lens", "Prime 35mm")]
+    #[case("exif.datetime", "2021-07-14 09:30:00")]
+    #[case("exif.datetime.year", "2021")]
+    #[case("exif.datetime.month", "07")]
+    #[case("exif.gps.latitude", "48.141667")]
+    #[case("exif.gps.longitude", "-11.566667")]
+    #[case("exif.gps.gpslatituderef", "N")]
+    fn test_read_exif_passes(#[case] key: &str, #[case] expected: &str) {
+        let values = read_exif(&get_fixture("photo.jpg")).unwrap();
+
+        assert_eq!(values[key], expected);
+    }
+
+    #[test]
+    fn test_read_exif_without_metadata_passes() {
+        assert!(read_exif(&get_fixture("no_exif.jpg")).is_none());
+    }
+
+    #[rstest]
+    #[case("gps.gpslatitude", true)]
+    #[case("exif.gps.gpslatitude", true)]
+    #[case("model = test camera 1", true)]
+    #[case("model=Other Camera", false)]
+    #[case("exif.exif.flash", false)]
+    fn test_fulfills_condition_passes(#[case] condition: &str, #[case] expected: bool) {
+        let values = read_exif(&get_fixture("photo.jpg")).unwrap();
+
+        assert_eq!(fulfills_condition(&values, condition), expected);
+    }
+}
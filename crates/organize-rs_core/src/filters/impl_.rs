@@ -1,17 +1,24 @@
-use std::ops::Not;
+use std::{ops::Not, path::PathBuf};
 
 use filetime::FileTime;
 use itertools::Itertools;
 use jwalk::{ClientState, DirEntry};
+use regex::{Captures, Regex};
 
 use crate::{
-    error::FilterErrorKind,
+    actors::location_walker::DirEntryData,
+    error::{FilterErrorKind, OrganizeResult},
     filters::{
+        content::extract_text,
+        duplicates::DuplicateFinder,
+        exif_data::{fulfills_condition, read_exif},
+        script::ScriptFilter,
         CullKind, DateUnitKind, DuplicateKind, FilterApplicationKind, FilterClosure,
         FilterCollection, FilterGroup, FilterGroupOperationKind, FilterKind, FilterOperationKind,
-        NameFilterArgs, RecursiveFilterArgs,
+        NameFilterArgs, RecursiveFilterArgs, ValueFilterClosure,
     },
     parsers::{period_range::PeriodRange, size_range::SizeRange},
+    templating::TemplateValues,
 };
 
 impl FilterKind {
@@ -38,13 +45,22 @@ impl FilterKind {
             FilterKind::LastAccessed { range } => self.filter_by_last_accessed(range),
             FilterKind::Mimetype { mime: mimetype } => self.filter_by_mimetype(mimetype),
             FilterKind::Size { range } => self.filter_by_size(range),
-            FilterKind::Regex { expr: _ } => todo!("not implemented (yet)!"),
-            FilterKind::Exif { contains: _ } => todo!("not implemented (yet)!"),
-            FilterKind::FileContent { expr: _ } => todo!("not implemented (yet)!"),
-            FilterKind::Duplicate {
-                detect_original_by: _,
-                reverse: _,
-            } => todo!("not implemented (yet)!"),
+            FilterKind::Regex { expr } => {
+                let mut filter = self.filter_by_regex(expr);
+                Box::new(move |entry| filter(entry).is_some())
+            }
+            FilterKind::Exif { contains } => {
+                let mut filter = self.filter_by_exif(contains);
+                Box::new(move |entry| filter(entry).is_some())
+            }
+            FilterKind::FileContent { expr } => {
+                let mut filter = self.filter_by_file_content(expr);
+                Box::new(move |entry| filter(entry).is_some())
+            }
+            FilterKind::Script { source, path } => self.filter_by_script(source, path),
+            FilterKind::Duplicate { .. } => unreachable!(
+                "duplicate filters need to know all entries, use `FilterKind::get_filter_for_entries`"
+            ),
             #[cfg(target_os = "osx")]
             FilterKind::Added { date, mode } => todo!("not implemented (yet)!"),
             #[cfg(target_os = "osx")]
@@ -52,6 +68,108 @@ impl FilterKind {
         }
     }
 
+    /// Like [`FilterKind::get_filter`], but the closure returns the values
+    /// a matching entry provides to templates, e.g. the named capture
+    /// groups of [`FilterKind::Regex`] as `regex.<name>`
+    ///
+    /// The closure is meant to be created once and applied to all entries,
+    /// so expensive setup like compiling a regex happens only once.
+    pub fn get_filter_with_values<C: ClientState>(&self) -> ValueFilterClosure<C> {
+        match self {
+            FilterKind::Regex { expr } => self.filter_by_regex(expr),
+            FilterKind::FileContent { expr } => self.filter_by_file_content(expr),
+            FilterKind::Exif { contains } => self.filter_by_exif(contains),
+            _ => {
+                let mut filter = self.get_filter();
+                Box::new(move |entry| filter(entry).then(TemplateValues::new))
+            }
+        }
+    }
+
+    /// Like [`FilterKind::get_filter_with_values`], but filters that need
+    /// to know all entries beforehand, like [`FilterKind::Duplicate`],
+    /// examine `entries` once up front
+    pub fn get_filter_for_entries<'a, C: ClientState>(
+        &'a self,
+        entries: &DirEntryData,
+    ) -> ValueFilterClosure<'a, C> {
+        match self {
+            FilterKind::Duplicate {
+                detect_original_by,
+                reverse,
+            } => {
+                let duplicates = DuplicateFinder::new(*detect_original_by, *reverse)
+                    .find_duplicates(entries.iter().map(DirEntry::path));
+
+                Box::new(move |entry| {
+                    duplicates.get(&entry.path()).map(|original| {
+                        TemplateValues::from([(
+                            "duplicate.original".to_string(),
+                            original.to_string_lossy().to_string(),
+                        )])
+                    })
+                })
+            }
+            _ => self.get_filter_with_values(),
+        }
+    }
+
+    /// Checks that the regular expressions and scripts of the filter compile,
+    /// the filter matches nothing otherwise
+    ///
+    /// # Errors
+    ///
+    /// If a regular expression or script of the filter is invalid
+    pub fn check(&self) -> OrganizeResult<()> {
+        match self {
+            FilterKind::Regex { expr } | FilterKind::FileContent { expr } => {
+                _ = compile_regex(expr)?;
+            }
+            FilterKind::Script { source, path } => {
+                _ = ScriptFilter::compile(source.as_deref(), path.as_deref())?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn filter_by_regex<C: ClientState>(&self, expr: &str) -> ValueFilterClosure<'_, C> {
+        let regex = compile_regex(expr).ok();
+
+        Box::new(move |entry| {
+            let regex = regex.as_ref()?;
+            let captures = regex.captures(entry.file_name().to_str()?)?;
+
+            Some(capture_values(regex, &captures, "regex"))
+        })
+    }
+
+    fn filter_by_file_content<C: ClientState>(&self, expr: &str) -> ValueFilterClosure<'_, C> {
+        let regex = compile_regex(expr).ok();
+
+        Box::new(move |entry| {
+            let regex = regex.as_ref()?;
+            let text = extract_text(&entry.path())?;
+            let captures = regex.captures(&text)?;
+
+            Some(capture_values(regex, &captures, "content"))
+        })
+    }
+
+    fn filter_by_exif<C: ClientState>(&self, contains: &[String]) -> ValueFilterClosure<'_, C> {
+        let contains = contains.to_vec();
+
+        Box::new(move |entry| {
+            let values = read_exif(&entry.path())?;
+
+            contains
+                .iter()
+                .all(|condition| fulfills_condition(&values, condition))
+                .then_some(values)
+        })
+    }
+
     fn filter_by_extension<'a, 'args, C: ClientState>(
         &'a self,
         exts: &'args [String],
@@ -290,6 +408,22 @@ impl FilterKind {
         })
     }
 
+    /// Compiles the script once, the closure evaluates it for each entry
+    ///
+    /// If the script doesn't compile, no entry matches, [`FilterKind::check`]
+    /// reports it beforehand. Entries the script fails for don't match either.
+    fn filter_by_script<'a, 'args, C: ClientState>(
+        &'a self,
+        source: &'args Option<String>,
+        path: &'args Option<PathBuf>,
+    ) -> Box<dyn FnMut(&DirEntry<C>) -> bool + 'args> {
+        let Ok(filter) = ScriptFilter::compile(source.as_deref(), path.as_deref()) else {
+            return Box::new(|_entry| false);
+        };
+
+        Box::new(move |entry| filter.matches(&entry.path()).unwrap_or(false))
+    }
+
     fn filter_by_size<'a, 'args, C: ClientState>(
         &'a self,
         range: &'args Option<SizeRange>,
@@ -554,3 +688,25 @@ impl<T> FilterGroup<T> {
         &self.filters
     }
 }
+
+/// Compiles `expr`
+fn compile_regex(expr: &str) -> Result<Regex, FilterErrorKind> {
+    Regex::new(expr).map_err(|source| FilterErrorKind::InvalidRegex {
+        expr: expr.to_string(),
+        source,
+    })
+}
+
+/// The named capture groups as template values in the `namespace`,
+/// e.g. `regex.invoice_no`
+fn capture_values(regex: &Regex, captures: &Captures<'_>, namespace: &str) -> TemplateValues {
+    regex
+        .capture_names()
+        .flatten()
+        .filter_map(|name| {
+            captures
+                .name(name)
+                .map(|value| (format!("{namespace}.{name}"), value.as_str().to_string()))
+        })
+        .collect()
+}
@@ -208,6 +208,15 @@ impl Display for FilterKind {
     -> Regex
         Arguments:
         expr: {expr}
+            "
+            ),
+            FilterKind::Script { source, path } => write!(
+                f,
+                "
+    -> Script
+        Arguments:
+            source: {source:?},
+            path: {path:?}
             "
             ),
             FilterKind::Size { range } => {
@@ -0,0 +1,235 @@
+//! Rhai scripts as per-entry predicates
+
+use std::{
+    fs::{read_to_string, Metadata},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rhai::{CustomType, Dynamic, Engine, Scope, TypeBuilder, AST};
+
+use crate::error::{FilterErrorKind, OrganizeResult};
+
+/// Maximum number of operations a script may run for a single entry,
+/// so a runaway script can't stall the run
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Read-only view on an entry, scripts refer to it as `entry`
+#[derive(Debug, Clone)]
+struct ScriptEntry {
+    path: PathBuf,
+    metadata: Option<Metadata>,
+}
+
+impl ScriptEntry {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            metadata: path.metadata().ok(),
+        }
+    }
+
+    fn path(&mut self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    fn name(&mut self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn extension(&mut self) -> String {
+        self.path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn size(&mut self) -> Dynamic {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| i64::try_from(metadata.len()).ok())
+            .map_or(Dynamic::UNIT, Dynamic::from_int)
+    }
+
+    fn created(&mut self) -> Dynamic {
+        self.timestamp(Metadata::created)
+    }
+
+    fn last_modified(&mut self) -> Dynamic {
+        self.timestamp(Metadata::modified)
+    }
+
+    fn last_accessed(&mut self) -> Dynamic {
+        self.timestamp(Metadata::accessed)
+    }
+
+    /// The mime type, detected by the content of the file
+    fn mime(&mut self) -> String {
+        infer::get_from_path(&self.path)
+            .ok()
+            .flatten()
+            .map(|kind| kind.mime_type().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Seconds since the Unix epoch, `()` if the platform doesn't provide the time
+    fn timestamp(&self, time: impl Fn(&Metadata) -> std::io::Result<SystemTime>) -> Dynamic {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| time(metadata).ok())
+            .map_or(Dynamic::UNIT, |time| Dynamic::from_int(unix_seconds(time)))
+    }
+}
+
+impl CustomType for ScriptEntry {
+    fn build(mut builder: TypeBuilder<'_, Self>) {
+        #[allow(deprecated)] // The TypeBuilder api is volatile.
+        builder
+            .with_name("Entry")
+            .with_get("path", Self::path)
+            .with_get("name", Self::name)
+            .with_get("extension", Self::extension)
+            .with_get("size", Self::size)
+            .with_get("created", Self::created)
+            .with_get("last_modified", Self::last_modified)
+            .with_get("last_accessed", Self::last_accessed)
+            .with_get("mime", Self::mime);
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+        Err(err) => -i64::try_from(err.duration().as_secs()).unwrap_or(i64::MAX),
+    }
+}
+
+/// A script compiled once, that decides for each entry if it matches
+pub(crate) struct ScriptFilter {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptFilter {
+    /// Compiles the script given inline as `source` or in the file at `path`
+    ///
+    /// # Errors
+    ///
+    /// If neither or both of `source` and `path` are given, the file can't
+    /// be read or the script doesn't compile
+    pub(crate) fn compile(source: Option<&str>, path: Option<&Path>) -> OrganizeResult<Self> {
+        let source = match (source, path) {
+            (Some(source), None) => source.to_string(),
+            (None, Some(path)) => read_to_string(path)
+                .map_err(|err| FilterErrorKind::ReadingScriptFailed(path.to_path_buf(), err))?,
+            (None, None) => return Err(FilterErrorKind::ScriptMissing.into()),
+            (Some(_), Some(_)) => return Err(FilterErrorKind::ScriptAmbiguous.into()),
+        };
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine
+            .build_type::<ScriptEntry>()
+            .register_fn("now", || unix_seconds(SystemTime::now()));
+
+        let ast = engine
+            .compile(source)
+            .map_err(|err| FilterErrorKind::ScriptCompilationFailed(err.to_string()))?;
+
+        Ok(Self { engine, ast })
+    }
+
+    /// Evaluates the script for the item at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the script fails or doesn't return a boolean
+    pub(crate) fn matches(&self, path: &Path) -> OrganizeResult<bool> {
+        let mut scope = Scope::new();
+        _ = scope.push_constant("entry", ScriptEntry::new(path));
+
+        self.engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .map_err(|err| {
+                FilterErrorKind::ScriptEvaluationFailed {
+                    path: path.to_path_buf(),
+                    message: err.to_string(),
+                }
+                .into()
+            })
+    }
+}
+
+impl std::fmt::Debug for ScriptFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptFilter").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    use crate::error::OrganizeErrorKind;
+
+    use super::*;
+
+    #[rstest]
+    #[case(r#"entry.extension == "pdf""#, true)]
+    #[case(r#"entry.name.starts_with("invoice")"#, true)]
+    #[case("entry.size > 5", false)]
+    #[case("entry.size == 5", true)]
+    #[case("now() - entry.last_modified < 60", true)]
+    #[case(r#"entry.mime == """#, true)]
+    fn test_script_filter_matches_passes(#[case] source: &str, #[case] expected: bool) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("invoice.pdf");
+        write(&path, "12345").unwrap();
+
+        let filter = ScriptFilter::compile(Some(source), None).unwrap();
+
+        assert_eq!(filter.matches(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_script_filter_from_file_passes() {
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("filter.rhai");
+        write(&script, r#"let ext = entry.extension; ext == "rhai""#).unwrap();
+
+        let filter = ScriptFilter::compile(None, Some(&script)).unwrap();
+
+        assert!(filter.matches(&script).unwrap());
+    }
+
+    #[rstest]
+    #[case(None, None)]
+    #[case(Some("true"), Some(Path::new("filter.rhai")))]
+    #[case(Some("entry.size >"), None)]
+    fn test_script_filter_compile_fails(#[case] source: Option<&str>, #[case] path: Option<&Path>) {
+        let err = ScriptFilter::compile(source, path)
+            .unwrap_err()
+            .into_inner();
+
+        assert!(matches!(err, OrganizeErrorKind::Filter(_)), "{err:?}");
+    }
+
+    #[test]
+    fn test_script_filter_with_non_boolean_result_fails() {
+        let dir = tempdir().unwrap();
+        let filter = ScriptFilter::compile(Some("entry.name"), None).unwrap();
+
+        let err = filter.matches(dir.path()).unwrap_err().into_inner();
+
+        assert!(matches!(
+            err,
+            OrganizeErrorKind::Filter(FilterErrorKind::ScriptEvaluationFailed { .. })
+        ));
+    }
+}
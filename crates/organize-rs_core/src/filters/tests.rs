@@ -18,6 +18,7 @@ use pretty_assertions::assert_eq;
 use filetime::{self, FileTime};
 
 use crate::{
+    error::{FilterErrorKind, OrganizeErrorKind},
     filters::{FilterKind, NameFilterArgs},
     parsers::{period_range::PeriodRange, size_range::SizeRange},
 };
@@ -516,3 +517,99 @@ fn test_filter_ignore_multiple_strs_is_in_name_passes() {
     ]
     "###);
 }
+
+fn get_file_names(paths: Vec<PathBuf>) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .sorted()
+        .collect_vec()
+}
+
+#[rstest]
+fn test_filter_regex_matches_file_name_passes() {
+    let filter = FilterKind::Regex {
+        expr: r"^\d{3}test\d\.txt$".to_string(),
+    };
+
+    let (_, after) = get_base_values("by_name", filter);
+
+    assert_eq!(
+        get_file_names(after),
+        vec!["123test1.txt".to_string(), "456test2.txt".to_string()]
+    );
+}
+
+#[rstest]
+fn test_filter_regex_named_captures_as_template_values_passes() {
+    let filter = FilterKind::Regex {
+        expr: r"^(?P<number>\d+)test(?P<suffix>\d)?".to_string(),
+    };
+    let filter_with_values = filter.get_filter_with_values();
+
+    let values = get_fixture_entries("by_name")
+        .iter()
+        .filter(|entry| entry.file_name() == "123test1.txt")
+        .find_map(filter_with_values)
+        .expect("regex should match");
+
+    assert_eq!(values.len(), 2);
+    assert_eq!(values["regex.number"], "123");
+    assert_eq!(values["regex.suffix"], "1");
+}
+
+#[rstest]
+fn test_filter_regex_invalid_expression_matches_nothing_passes() {
+    let filter = FilterKind::Regex {
+        expr: r"^(?P<number>\d+".to_string(),
+    };
+
+    let (_, after) = get_base_values("by_name", filter);
+
+    assert!(after.is_empty());
+}
+
+#[rstest]
+#[case(FilterKind::Regex { expr: r"^(?P<number>\d+".to_string() })]
+#[case(FilterKind::FileContent { expr: "Invoice [".to_string() })]
+fn test_check_filter_with_invalid_expression_fails(#[case] filter: FilterKind) {
+    let error = filter.check().unwrap_err().into_inner();
+
+    assert!(matches!(
+        error,
+        OrganizeErrorKind::Filter(FilterErrorKind::InvalidRegex { .. })
+    ));
+}
+
+#[rstest]
+fn test_filter_file_content_matches_text_and_pdf_passes() {
+    let filter = FilterKind::FileContent {
+        expr: r"Invoice.*\s+Customer (?P<customer>\w+)".to_string(),
+    };
+
+    let (_, after) = get_base_values("file_content", filter);
+
+    assert_eq!(
+        get_file_names(after),
+        vec!["invoice.pdf".to_string(), "invoice.txt".to_string()]
+    );
+}
+
+#[rstest]
+fn test_filter_file_content_captures_as_template_values_passes() {
+    let filter = FilterKind::FileContent {
+        expr: r"Customer (?P<customer>\w+)".to_string(),
+    };
+    let filter_with_values = filter.get_filter_with_values();
+
+    let customers = get_fixture_entries("file_content")
+        .iter()
+        .filter_map(filter_with_values)
+        .map(|values| values["content.customer"].clone())
+        .sorted()
+        .collect_vec();
+
+    assert_eq!(customers, vec!["Acme".to_string(), "Globex".to_string()]);
+}
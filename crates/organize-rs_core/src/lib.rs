@@ -3,6 +3,7 @@ pub mod actors;
 pub mod aliases;
 pub mod concurrency;
 pub mod config;
+pub mod counters;
 pub mod error;
 pub mod filesystem;
 pub mod filters;
@@ -15,3 +16,4 @@ pub mod ser_de;
 pub mod state;
 pub mod tags;
 pub mod templating;
+pub mod validation;
@@ -1,6 +1,9 @@
 //! locations
 
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -155,6 +158,35 @@ impl Default for LocationKind {
     }
 }
 
+impl LocationKind {
+    /// Path of the location
+    pub fn path(&self) -> &Path {
+        match self {
+            LocationKind::NonRecursive { path, .. }
+            | LocationKind::RecursiveWithMaxDepth { path, .. }
+            | LocationKind::BarePath(path) => path,
+        }
+    }
+
+    /// The same location with a different path
+    pub(crate) fn with_path(&self, path: PathBuf) -> Self {
+        match self {
+            LocationKind::NonRecursive { target, .. } => LocationKind::NonRecursive {
+                path,
+                target: *target,
+            },
+            LocationKind::RecursiveWithMaxDepth {
+                max_depth, target, ..
+            } => LocationKind::RecursiveWithMaxDepth {
+                path,
+                max_depth: *max_depth,
+                target: *target,
+            },
+            LocationKind::BarePath(_) => LocationKind::BarePath(path),
+        }
+    }
+}
+
 impl Display for LocationKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -27,6 +27,11 @@ impl PeriodRange {
     pub fn in_range(&self, value: f64) -> bool {
         self.0.contains(&value)
     }
+
+    /// Returns `true` if no period is within the range
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl Display for PeriodRange {
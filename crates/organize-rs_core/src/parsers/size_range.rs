@@ -31,6 +31,11 @@ impl SizeRange {
     pub fn in_range(&self, size: f64) -> bool {
         self.0.contains(&size)
     }
+
+    /// Returns `true` if no size is within the range
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl FromStr for SizeRange {
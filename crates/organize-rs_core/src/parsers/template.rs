@@ -8,7 +8,7 @@ use winnow::{
 };
 
 pub fn parse_text_incl_underscore_hyphen(input: &str) -> IResult<&str, &str> {
-    take_while(1.., (AsChar::is_alpha, ('_'), ('-'))).parse_next(input)
+    take_while(1.., (AsChar::is_alphanum, ('_'), ('-'))).parse_next(input)
 }
 
 pub fn parse_boundaries(input: &str) -> IResult<&str, &str> {
@@ -58,11 +58,11 @@ fn parse_dot_separated_text(input: &str) -> IResult<&str, Vec<&str>> {
 }
 
 fn parse_template_content(input: &str) -> IResult<&str, &str> {
-    take_while(1.., (AsChar::is_alpha, "._()-%, '")).parse_next(input)
+    take_while(1.., |c: char| c != '{' && c != '}').parse_next(input)
 }
 
 fn parse_strftime_format(input: &str) -> IResult<&str, &str> {
-    delimited('\'', take_while(1.., (AsChar::is_alpha, "%-")), '\'').parse_next(input)
+    delimited('\'', take_while(1.., |c: char| c != '\''), '\'').parse_next(input)
 }
 
 pub fn parse_strftime_template(input: &str) -> IResult<&str, (&str, Vec<&str>, &str)> {
@@ -98,6 +98,7 @@ mod tests {
     #[should_panic]
     #[case("{metadata.extension}", ("", vec![""], ""))]
     #[case("{strftime(metadata.date_added, '%Y-%m-%d')}", ("strftime", vec!["metadata", "date_added"], "%Y-%m-%d"))]
+    #[case("{strftime(metadata.created, '%d.%m.%Y %H:%M')}", ("strftime", vec!["metadata", "created"], "%d.%m.%Y %H:%M"))]
     fn test_parse_strftime_template_passes(
         #[case] template: &str,
         #[case] outcome: (&str, Vec<&str>, &str),
@@ -1,28 +1,41 @@
 //! organize-py config
+//!
+//! Configs of the Python version of organize are converted into an
+//! [`OrganizeConfig`] with [`PyOrganizeConfig::convert`]. Filters and
+//! actions are kept in their raw form until then, so a single filter or
+//! action that can't be translated doesn't prevent converting the rest of
+//! the config.
 
-use displaydoc::Display;
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
 use crate::{
-    actions::ActionApplicationCollection,
-    filters::{FilterApplicationKind, FilterKind},
-    locations::LocationCollection,
-    tags::TagCollection,
+    actions::{ActionApplicationKind, ActionContainer, ActionKind},
+    config::OrganizeConfig,
+    error::{ConfigErrorKind, OrganizeResult},
+    filters::{
+        DateUnitKind, FilterApplicationKind, FilterGroup, FilterGroupOperationKind, FilterKind,
+    },
+    locations::{LocationKind, MaxDepth, TargetKind},
+    rules::Rule,
+    tags::Tag,
 };
 
+/// Depth used for locations with `subfolders: true` and no `max_depth`
+const UNLIMITED_DEPTH: u64 = u32::MAX as u64;
+
 /// Organize Configuration
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 // #[serde(deny_unknown_fields)]
 #[serde(default)]
 pub struct PyOrganizeConfig {
-    // aliases: Vec<Reference>,
+    // aliases are resolved by YAML anchors
     rules: PyRules,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Display, Default)]
-#[serde(transparent)]
-pub struct PyFilterApplicationCollection(Vec<FilterKind>);
-
 /// [`PyRules`] contains a list of [`PyRule`] objects with the required keys
 /// "locations" and "actions". One config can have many [`PyRules`].
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -31,21 +44,1076 @@ pub struct PyRules(Vec<PyRule>);
 
 /// [`PyRule`] contains a objects with the required keys
 /// "locations" and "actions". One config can have many [`PyRules].
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default, rename = "rule")]
 pub struct PyRule {
     /// rule name
-    name: String,
+    name: Option<String>,
     /// tag for a rule, so you can run a set of rules by passing `--tags` or `--skip-tags`
-    tags: TagCollection,
+    tags: Vec<String>,
     /// whether the rule is enabled / disabled
     enabled: bool,
+    /// whether to recurse into subfolders of all locations
+    subfolders: bool,
     /// whether "all", "any" or "none" of the filters must apply
-    filter_mode: FilterApplicationKind,
-    /// list of locations
-    locations: LocationCollection,
+    filter_mode: Option<String>,
+    /// whether the rule works on "files" or "dirs"
+    targets: Option<String>,
+    /// a single location or a list of locations, either paths or maps with a `path`
+    locations: Value,
     /// supported filters
-    filters: PyFilterApplicationCollection,
+    filters: Vec<serde_yaml::Value>,
     /// supported actions
-    actions: ActionApplicationCollection,
+    actions: Vec<serde_yaml::Value>,
+}
+
+impl Default for PyRule {
+    fn default() -> Self {
+        Self {
+            name: None,
+            tags: vec![],
+            enabled: true,
+            subfolders: false,
+            filter_mode: None,
+            targets: None,
+            locations: Value::Null,
+            filters: vec![],
+            actions: vec![],
+        }
+    }
+}
+
+/// Something in a Python config, that couldn't be translated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyConversionIssue {
+    /// name of the rule the issue belongs to
+    pub rule: String,
+    /// description of the issue
+    pub message: String,
+}
+
+impl Display for PyConversionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rule `{}`: {}", self.rule, self.message)
+    }
+}
+
+/// The result of converting a [`PyOrganizeConfig`]
+#[derive(Debug, Clone)]
+pub struct PyConversion {
+    /// the converted config, without everything that couldn't be translated
+    pub config: OrganizeConfig,
+    /// everything that couldn't be translated, in the order of the rules
+    pub issues: Vec<PyConversionIssue>,
+}
+
+impl PyOrganizeConfig {
+    /// Parses a YAML config of the Python version of organize
+    ///
+    /// # Errors
+    ///
+    /// If the config is not valid YAML or doesn't have the expected structure
+    pub fn load_from_string(string: &str) -> OrganizeResult<Self> {
+        Ok(serde_yaml::from_str(string).map_err(ConfigErrorKind::YamlError)?)
+    }
+
+    /// Reads and parses a YAML config of the Python version of organize
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or parsed
+    pub fn load_from_file(path: impl AsRef<Path>) -> OrganizeResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|source| {
+            ConfigErrorKind::ReadingConfigFileFailed {
+                path: path.to_path_buf(),
+                source,
+            }
+        })?;
+
+        Self::load_from_string(&content)
+    }
+
+    /// Converts the config into an [`OrganizeConfig`]
+    ///
+    /// Filters, actions and placeholders that can't be translated are left
+    /// out and reported as issues. All actions are converted to preview
+    /// actions, so the converted config can be checked before running it
+    /// destructively.
+    pub fn convert(&self) -> PyConversion {
+        let mut config = OrganizeConfig::new();
+        let mut issues = vec![];
+
+        for (index, rule) in self.rules.0.iter().enumerate() {
+            let name = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Rule {}", index + 1));
+            let mut converter = RuleConverter {
+                rule: name,
+                issues: &mut issues,
+            };
+            config.add_rule(converter.convert(rule));
+        }
+
+        PyConversion { config, issues }
+    }
+}
+
+/// Converts a single [`PyRule`] and collects its issues
+struct RuleConverter<'a> {
+    rule: String,
+    issues: &'a mut Vec<PyConversionIssue>,
+}
+
+impl RuleConverter<'_> {
+    fn report(&mut self, message: String) {
+        self.issues.push(PyConversionIssue {
+            rule: self.rule.clone(),
+            message,
+        });
+    }
+
+    fn convert(&mut self, rule: &PyRule) -> Rule {
+        let mut builder = Rule::builder().name(&self.rule).enabled(rule.enabled);
+
+        let target = match rule.targets.as_deref() {
+            None | Some("files") => TargetKind::Files,
+            Some("dirs") => TargetKind::Directories,
+            Some(other) => {
+                self.report(format!("targets `{other}` is not supported, using `files`"));
+                TargetKind::Files
+            }
+        };
+        for location in self.convert_locations(&rule.locations, rule.subfolders, target) {
+            builder = builder.location(location);
+        }
+
+        for filter_group in self.convert_filters(rule) {
+            builder = builder.filter_group(filter_group);
+        }
+
+        for action in &rule.actions {
+            if let Some(action) = self.convert_action(action) {
+                builder = builder.action(ActionContainer {
+                    mode: ActionApplicationKind::Preview,
+                    action,
+                });
+            }
+        }
+
+        for tag in &rule.tags {
+            if let Ok(tag) = Tag::from_str(tag) {
+                builder = builder.tag(tag);
+            }
+        }
+
+        builder.build()
+    }
+
+    fn convert_locations(
+        &mut self,
+        locations: &Value,
+        subfolders: bool,
+        target: TargetKind,
+    ) -> Vec<LocationKind> {
+        let locations = match locations {
+            Value::Null => {
+                self.report("rule has no locations".to_string());
+                return vec![];
+            }
+            Value::Array(locations) => locations.clone(),
+            location => vec![location.clone()],
+        };
+
+        locations
+            .iter()
+            .filter_map(|location| {
+                let (path, max_depth) = match location {
+                    Value::String(path) => (path.as_str(), None),
+                    Value::Object(options) => {
+                        let Some(path) = options.get("path").and_then(Value::as_str) else {
+                            self.report(format!("location {location} has no path"));
+                            return None;
+                        };
+                        for option in options.keys() {
+                            if option != "path" && option != "max_depth" {
+                                self.report(format!("location option `{option}` is not supported"));
+                            }
+                        }
+                        (path, options.get("max_depth").and_then(Value::as_u64))
+                    }
+                    _ => {
+                        self.report(format!("location {location} is not supported"));
+                        return None;
+                    }
+                };
+                let path = self.translate_placeholders(path, "location").into();
+
+                Some(if subfolders {
+                    LocationKind::RecursiveWithMaxDepth {
+                        path,
+                        max_depth: MaxDepth::new(max_depth.unwrap_or(UNLIMITED_DEPTH)),
+                        target,
+                    }
+                } else {
+                    LocationKind::NonRecursive { path, target }
+                })
+            })
+            .collect()
+    }
+
+    /// Converts the filters into a group of filters and a group of the
+    /// negated (`not ...`) filters, that excludes items
+    fn convert_filters(&mut self, rule: &PyRule) -> Vec<FilterGroup<Vec<FilterKind>>> {
+        let mode = match rule.filter_mode.as_deref() {
+            None | Some("all") => FilterApplicationKind::All,
+            Some("any") => FilterApplicationKind::Any,
+            Some("none") => FilterApplicationKind::None,
+            Some(other) => {
+                self.report(format!(
+                    "filter_mode `{other}` is not supported, using `all`"
+                ));
+                FilterApplicationKind::All
+            }
+        };
+
+        let mut filters = vec![];
+        let mut negated_filters = vec![];
+        for filter in &rule.filters {
+            match self.convert_filter(filter, mode) {
+                Some((false, filter)) => filters.push(filter),
+                Some((true, filter)) => negated_filters.push(filter),
+                None => (),
+            }
+        }
+
+        let mut filter_groups = vec![];
+        if !filters.is_empty() {
+            filter_groups.push(FilterGroup::new(
+                FilterGroupOperationKind::Include,
+                mode,
+                filters,
+            ));
+        }
+        if !negated_filters.is_empty() {
+            filter_groups.push(FilterGroup::new(
+                FilterGroupOperationKind::Exclude,
+                FilterApplicationKind::Any,
+                negated_filters,
+            ));
+        }
+        filter_groups
+    }
+
+    /// Converts a filter, returns whether it is negated
+    fn convert_filter(
+        &mut self,
+        filter: &serde_yaml::Value,
+        mode: FilterApplicationKind,
+    ) -> Option<(bool, FilterKind)> {
+        let (name, args) = self.split_entry(filter, "filter")?;
+        let (negated, name) = match name.strip_prefix("not ") {
+            Some(name) => (true, name.trim()),
+            None => (false, name.as_str()),
+        };
+        if negated && mode != FilterApplicationKind::All {
+            self.report(format!(
+                "negated filter `{name}` is only supported with filter_mode `all`"
+            ));
+            return None;
+        }
+
+        let value = match (name, &args) {
+            ("extension", Value::Object(args)) if args.contains_key("exts") => {
+                json!({ "extension": { "exts": self.extensions(&args["exts"])? } })
+            }
+            ("extension", args) => json!({ "extension": { "exts": self.extensions(args)? } }),
+            ("name", Value::String(pattern)) => {
+                json!({ "regex": { "expr": self.name_pattern(std::slice::from_ref(pattern), false)? } })
+            }
+            ("name", Value::Object(args)) if args.contains_key("match") => {
+                let case_insensitive = args
+                    .get("case_sensitive")
+                    .and_then(Value::as_bool)
+                    .is_some_and(|case_sensitive| !case_sensitive);
+                if let Some(other) = args
+                    .keys()
+                    .find(|key| !matches!(key.as_str(), "match" | "case_sensitive"))
+                {
+                    self.report(format!(
+                        "`name` option `match` can't be combined with `{other}`"
+                    ));
+                    return None;
+                }
+                let expr = self.name_pattern(&strings(&args["match"]), case_insensitive)?;
+                json!({ "regex": { "expr": expr } })
+            }
+            ("name", Value::Object(args)) => {
+                let mut converted = Map::new();
+                for (key, value) in args {
+                    let key = match key.as_str() {
+                        "startswith" => "starts_with",
+                        "contains" => "contains",
+                        "endswith" => "ends_with",
+                        "case_sensitive" => {
+                            let case_sensitive = value.as_bool().unwrap_or(true);
+                            _ = converted
+                                .insert("case_insensitive".into(), Value::Bool(!case_sensitive));
+                            continue;
+                        }
+                        other => {
+                            self.report(format!("`name` option `{other}` is not supported"));
+                            continue;
+                        }
+                    };
+                    _ = converted.insert(key.into(), json!(strings(value)));
+                }
+                json!({ "name": converted })
+            }
+            ("empty", _) => json!("empty"),
+            ("hash", _) => json!("hash"),
+            ("created" | "lastmodified", Value::Object(args)) => {
+                let kind = if name == "created" {
+                    "created"
+                } else {
+                    "last_modified"
+                };
+                json!({ kind: { "range": self.period_range(args)? } })
+            }
+            ("size", args) => json!({ "size": { "range": self.size_range(args)? } }),
+            ("regex", Value::String(expr)) => json!({ "regex": { "expr": expr } }),
+            ("regex", Value::Object(args)) => json!({ "regex": args }),
+            ("filecontent", Value::String(expr)) => json!({ "file_content": { "expr": expr } }),
+            ("filecontent", Value::Object(args)) => json!({ "file_content": args }),
+            ("duplicate", Value::Null) => json!({ "duplicate": {} }),
+            ("duplicate", Value::Object(args)) => {
+                let detect_original_by = args
+                    .get("detect_original_by")
+                    .and_then(Value::as_str)
+                    .unwrap_or("first_seen");
+                let (reverse, detect_original_by) = match detect_original_by.strip_prefix('-') {
+                    Some(detect_original_by) => (true, detect_original_by),
+                    None => (false, detect_original_by),
+                };
+                let detect_original_by = match detect_original_by {
+                    "lastmodified" => "last_modified",
+                    other => other,
+                };
+                json!({ "duplicate": { "detect_original_by": detect_original_by, "reverse": reverse } })
+            }
+            ("exif", Value::Null) => json!({ "exif": { "contains": [] } }),
+            ("exif", Value::Object(args)) => {
+                let contains: Vec<String> = args
+                    .iter()
+                    .map(|(tag, value)| match value {
+                        Value::Null => tag.clone(),
+                        Value::String(value) => format!("{tag}={value}"),
+                        value => format!("{tag}={value}"),
+                    })
+                    .collect();
+                json!({ "exif": { "contains": contains } })
+            }
+            ("mimetype", args) if !args.is_null() => {
+                json!({ "mimetype": { "mime": strings(args).join(",") } })
+            }
+            (
+                "created" | "lastmodified" | "mimetype" | "name" | "regex" | "filecontent"
+                | "duplicate" | "exif",
+                _,
+            ) => {
+                self.report(format!("filter `{name}` with {args} is not supported"));
+                return None;
+            }
+            _ => {
+                self.report(format!("filter `{name}` is not supported"));
+                return None;
+            }
+        };
+
+        match serde_json::from_value(value) {
+            Ok(filter) => Some((negated, filter)),
+            Err(err) => {
+                self.report(format!("filter `{name}` can't be converted: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Translates the patterns of a `name` filter into a regular expression,
+    /// that matches the file stem like the Python version does
+    ///
+    /// Only `*` wildcards are supported, patterns with placeholders are
+    /// reported.
+    fn name_pattern(&mut self, patterns: &[String], case_insensitive: bool) -> Option<String> {
+        if patterns.is_empty() {
+            self.report("`name` filter without a pattern is not supported".to_string());
+            return None;
+        }
+        if let Some(pattern) = patterns.iter().find(|pattern| pattern.contains('{')) {
+            self.report(format!(
+                "`name` pattern `{pattern}` with placeholders can't be translated"
+            ));
+            return None;
+        }
+
+        let alternatives = patterns
+            .iter()
+            .map(|pattern| pattern.split('*').map(regex::escape).join(".*"))
+            .join("|");
+        let flags = if case_insensitive { "(?i)" } else { "" };
+
+        // the regex filter matches the file name, so allow the extension
+        Some(format!(r"{flags}^(?:{alternatives})(?:\.[^.]*)?$"))
+    }
+
+    fn convert_action(&mut self, action: &serde_yaml::Value) -> Option<ActionKind> {
+        let (name, args) = self.split_entry(action, "action")?;
+
+        let value = match (name.as_str(), &args) {
+            ("move" | "copy", Value::String(dst)) => {
+                json!({ name.as_str(): { "dst": self.translate_placeholders(dst, "dest") } })
+            }
+            ("move" | "copy", Value::Object(args)) => {
+                let Some(dst) = args.get("dest").and_then(Value::as_str) else {
+                    self.report(format!("action `{name}` has no `dest`"));
+                    return None;
+                };
+                let mut converted = self.conflict_options(&name, args);
+                _ = converted.insert(
+                    "dst".into(),
+                    Value::String(self.translate_placeholders(dst, "dest")),
+                );
+                json!({ name.as_str(): converted })
+            }
+            ("rename", Value::String(new_name)) => {
+                json!({ "rename": { "name": self.translate_placeholders(new_name, "new_name") } })
+            }
+            ("rename", Value::Object(args)) => {
+                let Some(new_name) = args.get("new_name").and_then(Value::as_str) else {
+                    self.report("action `rename` has no `new_name`".to_string());
+                    return None;
+                };
+                let mut converted = self.conflict_options(&name, args);
+                _ = converted.insert(
+                    "name".into(),
+                    Value::String(self.translate_placeholders(new_name, "new_name")),
+                );
+                json!({ "rename": converted })
+            }
+            ("trash", _) => json!("trash"),
+            ("delete", _) => json!("delete"),
+            ("echo", Value::String(msg)) => {
+                json!({ "echo": { "msg": self.translate_placeholders(msg, "msg") } })
+            }
+            ("echo", Value::Object(args)) if args.contains_key("msg") => {
+                let msg = args["msg"].as_str().unwrap_or_default();
+                json!({ "echo": { "msg": self.translate_placeholders(msg, "msg") } })
+            }
+            ("confirm", Value::Null) => json!({ "confirm": {} }),
+            ("confirm", Value::String(msg)) => {
+                json!({ "confirm": { "msg": self.translate_placeholders(msg, "msg") } })
+            }
+            ("confirm", Value::Object(args)) => {
+                let msg = args.get("msg").and_then(Value::as_str).unwrap_or_default();
+                json!({ "confirm": { "msg": self.translate_placeholders(msg, "msg") } })
+            }
+            ("symlink", Value::String(dst)) => {
+                json!({ "symlink": { "dst": self.translate_placeholders(dst, "dest") } })
+            }
+            ("symlink", Value::Object(args)) if args.contains_key("dest") => {
+                let dst = args["dest"].as_str().unwrap_or_default();
+                json!({ "symlink": { "dst": self.translate_placeholders(dst, "dest") } })
+            }
+            ("shell", Value::String(cmd)) => json!({ "shell": { "command": cmd } }),
+            ("shell", Value::Object(args)) => {
+                let mut converted = args.clone();
+                if let Some(cmd) = converted.remove("cmd") {
+                    _ = converted.insert("command".into(), cmd);
+                }
+                json!({ "shell": converted })
+            }
+            ("write", Value::Object(args)) => {
+                let text = args.get("text").and_then(Value::as_str).unwrap_or_default();
+                let file = args
+                    .get("outfile")
+                    .or_else(|| args.get("file"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("organize-out.txt");
+                if let Some(encoding) = args.get("encoding").and_then(Value::as_str) {
+                    if !encoding.eq_ignore_ascii_case("utf-8") {
+                        self.report(format!(
+                            "encoding `{encoding}` of action `write` is not supported, using utf-8"
+                        ));
+                    }
+                }
+                json!({ "write": {
+                    "txt": self.translate_placeholders(text, "text"),
+                    "file": self.translate_placeholders(file, "outfile"),
+                    "mode": args.get("mode").cloned().unwrap_or_else(|| json!("append")),
+                    "newline": args.get("newline").cloned().unwrap_or(Value::Bool(true)),
+                    "clear_before_first_write": args
+                        .get("clear_before_first_write")
+                        .cloned()
+                        .unwrap_or(Value::Bool(false)),
+                } })
+            }
+            (
+                "move" | "copy" | "rename" | "echo" | "confirm" | "symlink" | "shell" | "write",
+                _,
+            ) => {
+                self.report(format!("action `{name}` with {args} is not supported"));
+                return None;
+            }
+            _ => {
+                self.report(format!("action `{name}` is not supported"));
+                return None;
+            }
+        };
+
+        match serde_json::from_value(value) {
+            Ok(action) => Some(action),
+            Err(err) => {
+                self.report(format!("action `{name}` can't be converted: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Translates `on_conflict` and `rename_template` of an action
+    fn conflict_options(&mut self, action: &str, args: &Map<String, Value>) -> Map<String, Value> {
+        let mut converted = Map::new();
+
+        if let Some(on_conflict) = args.get("on_conflict").and_then(Value::as_str) {
+            match on_conflict {
+                "skip" | "overwrite" | "trash" | "rename_new" | "rename_existing" => {
+                    _ = converted.insert("on_conflict".into(), json!(on_conflict));
+                }
+                other => self.report(format!(
+                    "on_conflict `{other}` of action `{action}` is not supported"
+                )),
+            }
+        }
+        if let Some(template) = args.get("rename_template").and_then(Value::as_str) {
+            let template = self.translate_placeholders(template, "rename_template");
+            _ = converted.insert("rename_template".into(), json!([template]));
+        }
+        if args.contains_key("filesystem") {
+            self.report(format!(
+                "filesystem of action `{action}` is not supported, using the local filesystem"
+            ));
+        }
+
+        converted
+    }
+
+    /// Splits a filter or action into its name and arguments
+    ///
+    /// Entries are either a name or a map with the name as first key. If
+    /// the arguments are empty, the remaining keys of the map are used, as
+    /// happens with arguments that are not indented below the name.
+    fn split_entry(&mut self, entry: &serde_yaml::Value, kind: &str) -> Option<(String, Value)> {
+        let (name, args) = match entry {
+            serde_yaml::Value::String(name) => (name.as_str(), serde_yaml::Value::Null),
+            serde_yaml::Value::Mapping(map) => match map.iter().next() {
+                Some((serde_yaml::Value::String(name), args)) => {
+                    let args = if args.is_null() && map.len() > 1 {
+                        serde_yaml::Value::Mapping(map.clone().into_iter().skip(1).collect())
+                    } else {
+                        args.clone()
+                    };
+                    (name.as_str(), args)
+                }
+                _ => {
+                    self.report(format!("{kind} without a name is not supported"));
+                    return None;
+                }
+            },
+            _ => {
+                self.report(format!("{kind} {entry:?} is not supported"));
+                return None;
+            }
+        };
+
+        match serde_json::to_value(args) {
+            Ok(args) => Some((name.trim().to_string(), args)),
+            Err(err) => {
+                self.report(format!("{kind} `{name}` can't be converted: {err}"));
+                None
+            }
+        }
+    }
+
+    fn extensions(&mut self, args: &Value) -> Option<Vec<String>> {
+        let extensions: Vec<String> = strings(args)
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .collect();
+
+        if extensions.is_empty() {
+            self.report("filter `extension` without extensions is not supported".to_string());
+            return None;
+        }
+        Some(extensions)
+    }
+
+    /// Translates the durations and `mode` of `created` and `lastmodified`
+    /// into a period range, e.g. `{days: 7, mode: newer}` into `..7d`
+    fn period_range(&mut self, args: &Map<String, Value>) -> Option<String> {
+        let units = [
+            ("years", "y"),
+            ("months", "mo"),
+            ("weeks", "w"),
+            ("days", "d"),
+            ("hours", "h"),
+            ("minutes", "m"),
+            ("seconds", "s"),
+        ];
+        let durations: Vec<(f64, &str)> = units
+            .iter()
+            .filter_map(|(key, unit)| Some((args.get(*key)?.as_f64()?, *unit)))
+            .filter(|(value, _)| *value > 0.0)
+            .collect();
+
+        let duration = match durations.as_slice() {
+            [] => {
+                self.report("period filters without a duration are not supported".to_string());
+                return None;
+            }
+            [(value, unit)] => format!("{value}{unit}"),
+            durations => {
+                let seconds: f64 = durations
+                    .iter()
+                    .map(|duration| DateUnitKind::from(*duration).into_seconds())
+                    .sum();
+                format!("{seconds}s")
+            }
+        };
+
+        match args.get("mode").and_then(Value::as_str).unwrap_or("older") {
+            "older" => Some(format!("{duration}..")),
+            "newer" => Some(format!("..{duration}")),
+            other => {
+                self.report(format!("period mode `{other}` is not supported"));
+                None
+            }
+        }
+    }
+
+    /// Translates size conditions, e.g. `[">= 1 MB", "< 1 GB"]` into a size
+    /// range like `1MB..1GB`
+    fn size_range(&mut self, args: &Value) -> Option<String> {
+        let mut lower = None;
+        let mut upper = None;
+
+        for condition in strings(args) {
+            let condition: String = condition.split_whitespace().collect();
+            if let Some(size) = condition
+                .strip_prefix(">=")
+                .or_else(|| condition.strip_prefix('>'))
+            {
+                lower = Some(size.to_string());
+            } else if let Some(size) = condition
+                .strip_prefix("<=")
+                .or_else(|| condition.strip_prefix('<'))
+            {
+                upper = Some(size.to_string());
+            } else {
+                self.report(format!("size condition `{condition}` is not supported"));
+                return None;
+            }
+        }
+
+        match (lower, upper) {
+            (None, None) => {
+                self.report("filter `size` without conditions is not supported".to_string());
+                None
+            }
+            (lower, upper) => Some(format!(
+                "{}..{}",
+                lower.unwrap_or_default(),
+                upper.unwrap_or_default()
+            )),
+        }
+    }
+
+    /// Translates the placeholders in `text`, e.g. `{name}` or
+    /// `{{ extension.upper() }}`, into templates
+    ///
+    /// Placeholders that can't be translated are kept as they are.
+    fn translate_placeholders(&mut self, text: &str, field: &str) -> String {
+        let mut translated = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find('{') {
+            translated.push_str(&rest[..start]);
+            let (open, close) = if rest[start..].starts_with("{{") {
+                ("{{", "}}")
+            } else {
+                ("{", "}")
+            };
+            let after_open = &rest[start + open.len()..];
+            let Some(end) = after_open.find(close) else {
+                translated.push_str(&rest[start..]);
+                return translated;
+            };
+
+            let placeholder = &rest[start..start + open.len() + end + close.len()];
+            match translate_placeholder(after_open[..end].trim()) {
+                Some(template) => translated.push_str(&format!("{{{template}}}")),
+                None => {
+                    self.report(format!(
+                        "{field}: placeholder `{placeholder}` can't be translated"
+                    ));
+                    translated.push_str(placeholder);
+                }
+            }
+            rest = &after_open[end + close.len()..];
+        }
+
+        translated.push_str(rest);
+        translated
+    }
+}
+
+/// The strings of a string, number or list of them
+fn strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(string) => vec![string.clone()],
+        Value::Number(number) => vec![number.to_string()],
+        Value::Array(values) => values.iter().flat_map(strings).collect(),
+        _ => vec![],
+    }
+}
+
+/// Translates the expression of a Python placeholder into a template,
+/// e.g. `created.year` into `metadata.created.year`
+fn translate_placeholder(expression: &str) -> Option<String> {
+    // Jinja filters, e.g. `name | upper`
+    let (expression, filter) = match expression.split_once('|') {
+        Some((expression, filter)) => (expression.trim(), Some(filter.trim())),
+        None => (expression, None),
+    };
+    // Python string methods, e.g. `name.upper()`
+    let (expression, method) = match expression.strip_suffix("()") {
+        Some(call) => match call.rsplit_once('.') {
+            Some((expression, method)) => (expression, Some(method)),
+            None => return None,
+        },
+        None => (expression, None),
+    };
+
+    let mut parts = expression.split('.');
+    let template = match (parts.next()?, parts.collect::<Vec<_>>().as_slice()) {
+        ("name", []) => "metadata.name".to_string(),
+        ("extension", []) => "metadata.extension".to_string(),
+        ("counter", []) => "utility.counter".to_string(),
+        ("created", date) => format!("metadata.created{}", date_attribute(date)?),
+        ("lastmodified", date) => format!("metadata.last_modified{}", date_attribute(date)?),
+        ("date_added", date) => format!("metadata.date_added{}", date_attribute(date)?),
+        ("regex", [group]) => format!("regex.{group}"),
+        ("filecontent", [group]) => format!("content.{group}"),
+        ("duplicate", ["original"]) => "duplicate.original".to_string(),
+        ("exif", keys) if !keys.is_empty() => format!("exif.{}", keys.join(".").to_lowercase()),
+        _ => return None,
+    };
+
+    match method.or(filter) {
+        None => Some(template),
+        Some("upper") => Some(format!("uppercase({template})")),
+        Some("lower") => Some(format!("lowercase({template})")),
+        Some(_) => None,
+    }
+}
+
+fn date_attribute(attributes: &[&str]) -> Option<String> {
+    match attributes {
+        [] => Some(String::new()),
+        [attribute @ ("year" | "month" | "day")] => Some(format!(".{attribute}")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::config::ConfigFileFormat;
+
+    use super::*;
+
+    fn issues(conversion: &PyConversion) -> Vec<String> {
+        conversion
+            .issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_convert_py_config_passes() {
+        let config = PyOrganizeConfig::load_from_string(
+            r#"
+rules:
+  - name: "Sort my invoices and receipts"
+    locations:
+      - ~/Downloads
+      - path: ~/Desktop
+        max_depth: 2
+    subfolders: true
+    tags:
+      - documents
+    filters:
+      - extension:
+          - pdf
+          - .docx
+      - name:
+          contains:
+            - Invoice
+            - Order
+          case_sensitive: false
+      - lastmodified:
+          days: 30
+          mode: older
+      - size: ["> 10 KB", "<= 5 MB"]
+      - not empty
+    actions:
+      - echo: "Moving {name}.{extension}"
+      - move:
+          dest: "~/Documents/{created.year}/{extension.upper()}/"
+          on_conflict: rename_new
+          rename_template: "{name}_{counter}"
+"#,
+        )
+        .unwrap();
+
+        let conversion = config.convert();
+
+        assert!(conversion.issues.is_empty(), "{:#?}", conversion.issues);
+        insta::assert_yaml_snapshot!(conversion.config.rules(), @r###"
+        - name: Sort my invoices and receipts
+          tags:
+            - custom: documents
+          enabled: true
+          locations:
+            - recursive:
+                path: ~/Downloads
+                max_depth: 4294967295
+                target: files
+            - recursive:
+                path: ~/Desktop
+                max_depth: 2
+                target: files
+          filter_groups:
+            - results: include
+              match: all
+              filters:
+                - extension:
+                    exts:
+                      - pdf
+                      - docx
+                - name:
+                    simple_match: ~
+                    starts_with: ~
+                    contains:
+                      - Invoice
+                      - Order
+                    ends_with: ~
+                    case_insensitive: true
+                - last_modified:
+                    range:
+                      start: 2592000
+                      end: 946700000
+                - size:
+                    range:
+                      start: 10000
+                      end: 5000000
+            - results: exclude
+              match: any
+              filters:
+                - empty
+          actions:
+            - mode: preview
+              action:
+                echo:
+                  msg: "Moving {metadata.name}.{metadata.extension}"
+            - mode: preview
+              action:
+                move:
+                  dst: "~/Documents/{metadata.created.year}/{uppercase(metadata.extension)}/"
+                  on_conflict: rename_new
+                  rename_template:
+                    - "{metadata.name}_{utility.counter}"
+                  filesystem: ~
+        "###);
+    }
+
+    #[test]
+    fn test_convert_py_config_reports_issues_passes() {
+        let config = PyOrganizeConfig::load_from_string(
+            r#"
+rules:
+  - locations: ~/Downloads
+    filter_mode: any
+    filters:
+      - python: |
+          return True
+      - not extension: jpg
+      - lastmodified:
+          days: 7
+          mode: newer
+    actions:
+      - rename: "{name}-{now()}.{extension}"
+      - macos_tags: Invoice
+"#,
+        )
+        .unwrap();
+
+        let conversion = config.convert();
+
+        insta::assert_debug_snapshot!(issues(&conversion), @r###"
+        [
+            "rule `Rule 1`: filter `python` is not supported",
+            "rule `Rule 1`: negated filter `extension` is only supported with filter_mode `all`",
+            "rule `Rule 1`: new_name: placeholder `{now()}` can't be translated",
+            "rule `Rule 1`: action `macos_tags` is not supported",
+        ]
+        "###);
+        let rule = &conversion.config.rules()[0];
+        assert_eq!(rule.filters().len(), 1);
+        assert_eq!(rule.actions().len(), 1);
+    }
+
+    #[test]
+    fn test_convert_unindented_filter_arguments_passes() {
+        let config = PyOrganizeConfig::load_from_string(
+            r#"
+rules:
+  - locations:
+      - ~/Downloads
+    filters:
+      - extension:
+        exts:
+          - pdf
+    actions:
+      - move: ~/Documents/Shopping/
+"#,
+        )
+        .unwrap();
+
+        let conversion = config.convert();
+
+        assert!(conversion.issues.is_empty(), "{:#?}", conversion.issues);
+        let filters = conversion.config.rules()[0].filters();
+        assert!(matches!(
+            filters[0].filters.as_slice(),
+            [FilterKind::Extension { exts }] if exts == &["pdf"]
+        ));
+    }
+
+    #[rstest]
+    #[case("name: Invoice*", &["Invoice_2023.pdf", "Invoice.txt"])]
+    #[case(
+        "name:\n          match: [\"*invoice\", report]\n          case_sensitive: false",
+        &["Invoice.txt", "Tax INVOICE.pdf", "report.pdf"]
+    )]
+    fn test_converted_name_pattern_matches_file_stem_passes(
+        #[case] filter: &str,
+        #[case] expected: &[&str],
+    ) {
+        let config = PyOrganizeConfig::load_from_string(&format!(
+            "rules:\n  - locations: ~/Downloads\n    filters:\n      - {filter}\n    actions:\n      - trash\n"
+        ))
+        .unwrap();
+        let conversion = config.convert();
+        assert!(conversion.issues.is_empty(), "{:#?}", conversion.issues);
+        let filter = &conversion.config.rules()[0].filters()[0].filters[0];
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "Invoice_2023.pdf",
+            "Invoice.txt",
+            "Tax INVOICE.pdf",
+            "report.pdf",
+            "report.pdf.bak",
+            "My Invoice Copy.pdf",
+        ] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let mut matches = filter.get_filter();
+        let matched: Vec<_> = jwalk::WalkDir::new(dir.path())
+            .min_depth(1)
+            .sort(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| matches(entry))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .sorted()
+            .collect();
+
+        assert_eq!(
+            matched,
+            expected.iter().copied().sorted().collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "name: \"{year}-*\"",
+        "rule `Rule 1`: `name` pattern `{year}-*` with placeholders can't be translated"
+    )]
+    #[case(
+        "name:\n          match: Invoice*\n          endswith: \"2023\"",
+        "rule `Rule 1`: `name` option `match` can't be combined with `endswith`"
+    )]
+    fn test_convert_untranslatable_name_pattern_reports_issue_passes(
+        #[case] filter: &str,
+        #[case] expected: &str,
+    ) {
+        let config = PyOrganizeConfig::load_from_string(&format!(
+            "rules:\n  - locations: ~/Downloads\n    filters:\n      - {filter}\n    actions:\n      - trash\n"
+        ))
+        .unwrap();
+
+        let conversion = config.convert();
+
+        assert_eq!(issues(&conversion), [expected]);
+        assert!(conversion.config.rules()[0].filters().is_empty());
+    }
+
+    #[test]
+    fn test_converted_config_round_trips_passes() {
+        let config = PyOrganizeConfig::load_from_string(
+            r#"
+rules:
+  - locations: ~/Downloads
+    filters:
+      - duplicate:
+          detect_original_by: -lastmodified
+    actions:
+      - trash
+"#,
+        )
+        .unwrap();
+        let converted = config.convert().config;
+
+        let yaml = converted.to_string_as(ConfigFileFormat::Yaml).unwrap();
+
+        assert!(OrganizeConfig::parse(&yaml, ConfigFileFormat::Yaml).is_ok());
+    }
+
+    #[rstest]
+    #[case("name", Some("metadata.name"))]
+    #[case("extension.upper()", Some("uppercase(metadata.extension)"))]
+    #[case("name | lower", Some("lowercase(metadata.name)"))]
+    #[case("created.month", Some("metadata.created.month"))]
+    #[case("lastmodified", Some("metadata.last_modified"))]
+    #[case("regex.number", Some("regex.number"))]
+    #[case("filecontent.customer", Some("content.customer"))]
+    #[case("exif.image.Model", Some("exif.image.model"))]
+    #[case("created.strftime('%Y')", None)]
+    #[case("env.HOME", None)]
+    fn test_translate_placeholder_passes(#[case] expression: &str, #[case] expected: Option<&str>) {
+        assert_eq!(translate_placeholder(expression).as_deref(), expected);
+    }
 }
@@ -13,6 +13,7 @@ use crate::{
         conflicts::ConflictResolutionKind, ActionApplicationCollection, ActionApplicationKind,
         ActionContainer, ActionKind,
     },
+    counters::CounterSettings,
     filters::{
         FilterApplicationKind, FilterGroup, FilterGroupCollection, FilterGroupOperationKind,
         FilterKind,
@@ -80,6 +81,9 @@ pub struct Rule {
     filter_groups: FilterGroupCollection,
     /// supported actions
     actions: ActionApplicationCollection,
+    /// how `{utility.counter}` numbers the items
+    #[serde(skip_serializing_if = "CounterSettings::is_default")]
+    counter: CounterSettings,
 }
 
 impl Display for Rule {
@@ -91,6 +95,7 @@ impl Display for Rule {
             locations,
             filter_groups: filters,
             actions,
+            counter: _,
         } = self;
 
         write!(
@@ -115,6 +120,10 @@ impl Rule {
         RuleBuilder::default()
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn locations(&self) -> LocationCollection {
         self.locations.clone()
     }
@@ -134,6 +143,22 @@ impl Rule {
     pub fn enabled(&self) -> bool {
         self.enabled
     }
+
+    pub fn counter(&self) -> &CounterSettings {
+        &self.counter
+    }
+
+    pub(crate) fn locations_mut(&mut self) -> &mut LocationCollection {
+        &mut self.locations
+    }
+
+    pub(crate) fn filters_mut(&mut self) -> &mut FilterGroupCollection {
+        &mut self.filter_groups
+    }
+
+    pub(crate) fn actions_mut(&mut self) -> &mut ActionApplicationCollection {
+        &mut self.actions
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -150,6 +175,8 @@ pub struct RuleBuilder {
     filter_groups: FilterGroupCollection,
     /// supported actions
     actions: ActionApplicationCollection,
+    /// how `{utility.counter}` numbers the items
+    counter: CounterSettings,
 }
 
 impl RuleBuilder {
@@ -168,6 +195,7 @@ impl RuleBuilder {
             locations: self.locations,
             filter_groups: self.filter_groups,
             actions: self.actions,
+            counter: self.counter,
         }
     }
 
@@ -219,6 +247,12 @@ impl RuleBuilder {
         self
     }
 
+    /// Set how `{utility.counter}` numbers the items
+    pub fn counter(mut self, counter: CounterSettings) -> RuleBuilder {
+        self.counter = counter;
+        self
+    }
+
     /// Add single tag
     pub fn tag(mut self, tag: Tag) -> RuleBuilder {
         self.tags.push(tag);
@@ -322,7 +356,7 @@ mod tests {
               max_depth: 1
               target: folders
         filter_groups:
-          - results: exclude
+          - results: include
             match: all
             filters:
               - empty
@@ -347,7 +381,7 @@ mod tests {
               max_depth: 4
               target: files
         filter_groups:
-          - results: exclude
+          - results: include
             match: all
             filters:
               - extension:
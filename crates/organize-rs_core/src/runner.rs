@@ -1,15 +1,24 @@
 use std::{collections::HashSet, path::Path};
 
+use console::style;
+use dialoguer::{Confirm, Select};
 use itertools::Itertools;
 
 use crate::{
-    actions::ActionResultKind,
-    actors::{filter_applicator::FilterApplicator, location_walker::LocationWalker},
+    actions::{
+        conflicts::ConflictResolutionKind, ActionApplicationKind, ActionClosure, ActionResultKind,
+        WrittenFiles,
+    },
+    actors::{
+        conflict_handler::{ConflictHandler, DetectedConflict},
+        filter_applicator::FilterApplicator,
+        location_walker::LocationWalker,
+    },
     config::OrganizeConfig,
-    error::OrganizeResult,
+    error::{ActionErrorKind, ConfigErrorKind, ConfigErrors, OrganizeError, OrganizeResult},
     state::{
         ActionApplication, ActionPreview, ConflictHandling, Filtering, Initialize, Inspection,
-        ProcessingStage,
+        ProcessingStage, Reporting,
     },
     tags::{Tag, TagCollection},
 };
@@ -25,17 +34,33 @@ where
 }
 
 impl Runner<Initialize> {
-    pub fn load_configs(paths: &[impl AsRef<Path>]) -> Runner<Filtering> {
-        let mut configs = vec![];
-        paths.iter().for_each(|path| {
-            let config = OrganizeConfig::load_from_file(path);
-            configs.push(config);
-        });
+    /// Loads the config files at `paths`, substitutes their aliases and
+    /// expands `~` and environment variables in their paths
+    ///
+    /// # Errors
+    ///
+    /// If any of the config files can't be loaded or their aliases or paths
+    /// can't be expanded, the errors of all of them are returned together
+    pub fn load_configs(paths: &[impl AsRef<Path>]) -> OrganizeResult<Runner<Filtering>> {
+        let (configs, errors): (Vec<_>, Vec<_>) = paths
+            .iter()
+            .map(|path| {
+                let mut config = OrganizeConfig::load_from_file(path)?;
+                config.expand_aliases()?;
+                config.expand_paths()?;
+                config.check_filters()?;
+                Ok::<_, OrganizeError>(config)
+            })
+            .partition_result();
 
-        Runner::<Filtering> {
+        if !errors.is_empty() {
+            return Err(ConfigErrorKind::LoadingConfigsFailed(ConfigErrors(errors)).into());
+        }
+
+        Ok(Runner::<Filtering> {
             configs,
             extra: Filtering::default(),
-        }
+        })
     }
 }
 
@@ -98,11 +123,19 @@ impl Runner<Inspection> {
 
 impl Runner<ActionPreview> {
     pub fn preview_actions(self) -> OrganizeResult<Runner<ActionApplication>> {
+        let resolutions = self.extra.resolutions().clone();
         let entries = self.extra.entries();
+        let written_files = WrittenFiles::default();
         entries.iter().for_each(|(rule, entry)| {
             rule.actions().iter().for_each(|action_container| {
+                let template_values = rule.counter().number_items(&action_container.action, entry);
+                let mut action = action_container.action.get_action_with(
+                    &resolutions,
+                    &template_values,
+                    &written_files,
+                );
                 entry.iter().for_each(|entry| {
-                    match action_container.action.get_action()(
+                    match action(
                         entry,
                         true, // * is always true, as it's preview
                              // ! for application we can use:
@@ -122,18 +155,88 @@ impl Runner<ActionPreview> {
 
         Ok(Runner::<ActionApplication> {
             configs: self.configs,
-            extra: ActionApplication::with_entries(entries),
+            extra: ActionApplication::with_entries(entries).with_resolutions(resolutions),
         })
     }
 }
 
 impl Runner<ActionApplication> {
-    pub fn apply_actions(self) -> OrganizeResult<Runner<ActionApplication>> {
-        todo!()
+    /// Applies the actions of every rule to its filtered entries
+    ///
+    /// Actions in `preview` mode are only previewed, actions in
+    /// `destructive` mode are executed and actions in `input` mode
+    /// are executed after the user confirmed them for each entry.
+    ///
+    /// The outcome of each action on each entry is collected, so
+    /// failures and conflicts can be reported afterwards.
+    pub fn apply_actions(self) -> OrganizeResult<Runner<Reporting>> {
+        let resolutions = self.extra.resolutions().clone();
+        let entries = self.extra.entries();
+        // shared by all rules, so each file is cleared only once per run
+        let written_files = WrittenFiles::default();
+        let mut outcomes = vec![];
+
+        entries.iter().for_each(|(rule, entry)| {
+            rule.actions().iter().for_each(|action_container| {
+                let template_values = rule.counter().number_items(&action_container.action, entry);
+                let mut action = action_container.action.get_action_with(
+                    &resolutions,
+                    &template_values,
+                    &written_files,
+                );
+                entry.iter().for_each(|entry| {
+                    let outcome = match action_container.mode {
+                        ActionApplicationKind::Preview => action(entry, true),
+                        ActionApplicationKind::Destructive => action(entry, false),
+                        ActionApplicationKind::UserInput => {
+                            match Self::ask_for_confirmation(&mut action, entry) {
+                                Ok(true) => action(entry, false),
+                                Ok(false) => Ok(ActionResultKind::Skipped),
+                                Err(err) => Err(err),
+                            }
+                        }
+                    };
+                    outcomes.push((entry.path(), outcome));
+                })
+            })
+        });
+
+        Ok(Runner::<Reporting> {
+            configs: self.configs,
+            extra: Reporting::with_outcomes(outcomes),
+        })
+    }
+
+    /// Shows the preview of an action for an entry and asks
+    /// the user, if it should be applied
+    fn ask_for_confirmation(
+        action: &mut ActionClosure<'_, ((), ())>,
+        entry: &jwalk::DirEntry<((), ())>,
+    ) -> OrganizeResult<bool> {
+        if let ActionResultKind::Preview { msg, .. } = action(entry, true)? {
+            println!("{msg}");
+        }
+
+        Confirm::new()
+            .with_prompt("Do you want to apply this action?")
+            .default(false)
+            .interact()
+            .map_err(|err| ActionErrorKind::UserInputFailed(err).into())
     }
 
+    /// Detects conflicts between the destinations of the actions and
+    /// already existing items or other items of this run
+    ///
+    /// Conflicts are resolved as configured for each action, unresolved
+    /// conflicts can be handled by [`Runner<ConflictHandling>::view_conflicts`].
     pub fn check_conflicts(self) -> Runner<ConflictHandling> {
-        todo!()
+        let entries = self.extra.entries();
+        let conflicts = ConflictHandler::new(&entries).detect_conflicts();
+
+        Runner::<ConflictHandling> {
+            configs: self.configs,
+            extra: ConflictHandling::with_conflicts(entries, conflicts),
+        }
     }
 }
 
@@ -146,7 +249,329 @@ impl Runner<ActionApplication> {
 //     }
 // }
 impl Runner<ConflictHandling> {
-    pub fn view_conflicts(self) -> Runner<ActionPreview> {
-        todo!()
+    /// Shows the detected conflicts and asks the user how to resolve
+    /// the ones, the configured conflict resolution couldn't resolve
+    ///
+    /// The chosen resolutions are used instead of the configured ones
+    /// for the corresponding items.
+    pub fn view_conflicts(self) -> OrganizeResult<Runner<ActionPreview>> {
+        let choices = [
+            ConflictResolutionKind::Skip,
+            ConflictResolutionKind::Overwrite,
+            ConflictResolutionKind::RenameNew,
+            ConflictResolutionKind::RenameExisting,
+            ConflictResolutionKind::Trash,
+        ];
+        let mut resolutions = vec![];
+
+        for conflict in self.extra.conflicts() {
+            println!(
+                "{} {}: '{}' -> '{}' ({})",
+                style("(Conflict)").yellow(),
+                conflict.kind,
+                conflict.path.display(),
+                conflict.destination.display(),
+                conflict.resolution
+            );
+
+            if conflict.is_unresolved() {
+                let choice = Select::new()
+                    .with_prompt("How should this conflict be resolved?")
+                    .items(&choices)
+                    .default(0)
+                    .interact()
+                    .map_err(ActionErrorKind::UserInputFailed)?;
+                resolutions.push((conflict.path.clone(), choices[choice].clone()));
+            }
+        }
+
+        Ok(Runner::<ActionPreview> {
+            configs: self.configs,
+            extra: ActionPreview::with_entries(self.extra.entries()).with_resolutions(resolutions),
+        })
+    }
+
+    /// Continues with the conflicts resolved as configured for the actions
+    pub fn finish_conflict_handling(self) -> Runner<ActionApplication> {
+        Runner::<ActionApplication> {
+            configs: self.configs,
+            extra: ActionApplication::with_entries(self.extra.entries()),
+        }
+    }
+
+    pub fn conflicts(&self) -> &[DetectedConflict] {
+        self.extra.conflicts()
+    }
+
+    /// Returns `true` if any conflict couldn't be resolved as configured
+    pub fn has_unresolved_conflicts(&self) -> bool {
+        self.extra
+            .conflicts()
+            .iter()
+            .any(DetectedConflict::is_unresolved)
+    }
+}
+
+impl Runner<Reporting> {
+    pub fn print_report(self) -> Runner<Reporting> {
+        self.extra.print_report();
+        self
+    }
+
+    pub fn report(&self) -> &Reporting {
+        &self.extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::PathBuf};
+
+    use crate::{
+        actions::{ActionApplicationKind, ActionContainer, ActionKind, WriteModeKind},
+        error::OrganizeErrorKind,
+        filters::{FilterApplicationKind, FilterGroup, FilterGroupOperationKind, FilterKind},
+        locations::{LocationKind, MaxDepth, TargetKind},
+        rules::Rule,
+    };
+
+    use super::*;
+
+    fn all_files_rule(path: &Path, max_depth: u64, action: ActionContainer) -> Rule {
+        Rule::builder()
+            .name("All files")
+            .enabled(true)
+            .location(LocationKind::RecursiveWithMaxDepth {
+                path: path.to_path_buf(),
+                max_depth: MaxDepth::new(max_depth),
+                target: TargetKind::Files,
+            })
+            .filter_group(FilterGroup::new(
+                FilterGroupOperationKind::Include,
+                FilterApplicationKind::All,
+                vec![FilterKind::AllItems {
+                    i_agree_it_is_dangerous: true,
+                }],
+            ))
+            .action(action)
+            .build()
+    }
+
+    fn delete_all_files_rule(path: &Path, mode: ActionApplicationKind) -> Rule {
+        all_files_rule(
+            path,
+            1,
+            ActionContainer {
+                mode,
+                action: ActionKind::Delete,
+            },
+        )
+    }
+
+    fn runner_with_rule(rule: Rule) -> Runner<Filtering> {
+        let mut config = OrganizeConfig::new();
+        config.add_rule(rule);
+
+        Runner::<Filtering> {
+            configs: vec![config],
+            extra: Filtering,
+        }
+    }
+
+    #[test]
+    fn test_load_configs_collects_all_errors_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let valid = dir.path().join("valid.yaml");
+        let invalid = dir.path().join("invalid.yaml");
+        std::fs::write(&valid, "rules: []").unwrap();
+        std::fs::write(&invalid, "rules: {").unwrap();
+        let paths = [valid, invalid, dir.path().join("missing.toml")];
+
+        let Err(error) = Runner::<Initialize>::load_configs(&paths) else {
+            panic!("loading invalid configs should fail");
+        };
+
+        let OrganizeErrorKind::Config(ConfigErrorKind::LoadingConfigsFailed(errors)) =
+            error.into_inner()
+        else {
+            panic!("errors should be collected");
+        };
+        assert_eq!(errors.0.len(), 2);
+    }
+
+    #[test]
+    fn test_load_configs_with_invalid_regex_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            format!(
+                r#"
+rules:
+  - name: Invoices
+    locations:
+      - !non_recursive
+        path: {}
+        target: files
+    filter_groups:
+      - filters:
+          - !regex
+            expr: '^(?P<number>\d+'
+        results: include
+        match: all
+    actions:
+      - mode: preview
+        action: !trash
+"#,
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let Err(error) = Runner::<Initialize>::load_configs(&[path]) else {
+            panic!("an invalid regular expression should be rejected");
+        };
+
+        assert!(
+            error.to_string().contains("invalid regular expression"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_apply_actions_destructive_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("to_be_deleted.txt");
+        _ = File::create(&file_path).unwrap();
+
+        let runner = runner_with_rule(delete_all_files_rule(
+            dir.path(),
+            ActionApplicationKind::Destructive,
+        ))
+        .apply_filters(vec![])
+        .finish_inspection()
+        .preview_actions()
+        .unwrap()
+        .apply_actions()
+        .unwrap();
+
+        assert!(!file_path.exists());
+        assert_eq!(runner.report().successful_count(), 1);
+        assert_eq!(runner.report().failed_count(), 0);
+    }
+
+    #[test]
+    fn test_apply_actions_preview_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("to_be_kept.txt");
+        _ = File::create(&file_path).unwrap();
+
+        let runner = runner_with_rule(delete_all_files_rule(
+            dir.path(),
+            ActionApplicationKind::Preview,
+        ))
+        .apply_filters(vec![])
+        .finish_inspection()
+        .preview_actions()
+        .unwrap()
+        .apply_actions()
+        .unwrap();
+
+        assert!(file_path.exists());
+        assert_eq!(runner.report().outcomes().len(), 1);
+        assert_eq!(runner.report().successful_count(), 0);
+    }
+
+    #[test]
+    fn test_apply_actions_clears_file_once_per_run_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let documents = dir.path().join("documents");
+        std::fs::create_dir(&documents).unwrap();
+        _ = File::create(documents.join("invoice.pdf")).unwrap();
+        let inventory = dir.path().join("inventory.txt");
+        std::fs::write(&inventory, "previous run\n").unwrap();
+        let write_rule = |txt: &str| {
+            all_files_rule(
+                &documents,
+                1,
+                ActionContainer {
+                    mode: ActionApplicationKind::Destructive,
+                    action: ActionKind::Write {
+                        txt: txt.to_string(),
+                        file: inventory.clone(),
+                        mode: WriteModeKind::Append,
+                        newline: true,
+                        clear_before_first_write: true,
+                        filesystem: None,
+                    },
+                },
+            )
+        };
+        let mut config = OrganizeConfig::new();
+        config.add_rule(write_rule("first rule"));
+        config.add_rule(write_rule("second rule"));
+
+        for _ in 0..2 {
+            let runner = Runner::<Filtering> {
+                configs: vec![config.clone()],
+                extra: Filtering,
+            }
+            .apply_filters(vec![])
+            .finish_inspection()
+            .preview_actions()
+            .unwrap()
+            .apply_actions()
+            .unwrap();
+
+            assert_eq!(runner.report().successful_count(), 2);
+            assert_eq!(
+                std::fs::read_to_string(&inventory).unwrap(),
+                "first rule\nsecond rule\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_actions_with_conflicts_of_same_run_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        let archive = dir.path().join("archive");
+        for sub_dir in ["a", "b"] {
+            std::fs::create_dir_all(inbox.join(sub_dir)).unwrap();
+            _ = File::create(inbox.join(sub_dir).join("report.txt")).unwrap();
+        }
+        let rule = all_files_rule(
+            &inbox,
+            2,
+            ActionContainer {
+                mode: ActionApplicationKind::Destructive,
+                action: ActionKind::Move {
+                    dst: PathBuf::from(format!(
+                        "{}{}",
+                        archive.display(),
+                        std::path::MAIN_SEPARATOR
+                    )),
+                    on_conflict: ConflictResolutionKind::RenameNew,
+                    rename_template: None,
+                    filesystem: None,
+                },
+            },
+        );
+
+        let runner = runner_with_rule(rule)
+            .apply_filters(vec![])
+            .finish_inspection()
+            .preview_actions()
+            .unwrap()
+            .check_conflicts();
+
+        assert_eq!(runner.conflicts().len(), 1);
+        assert!(!runner.has_unresolved_conflicts());
+
+        let runner = runner.finish_conflict_handling().apply_actions().unwrap();
+
+        assert_eq!(runner.report().successful_count(), 2);
+        assert!(archive.join("report.txt").exists());
+        assert!(archive.join("report_1.txt").exists());
     }
 }
@@ -2,12 +2,13 @@
 
 #[cfg(test)]
 mod tests {
+    use crate::py_config::PyOrganizeConfig;
 
     type TestResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
     #[test]
     pub fn test_single_rule_deserialisation_passes() -> TestResult<()> {
-        let _rules = r#"
+        let rules = r#"
 rules:
   - name: "Sort my invoices and receipts"
     locations: 
@@ -41,8 +42,10 @@ rules:
     actions:
       - move: ~/Documents/Shopping/
 "#;
-        // let value = serde_yaml::from_str::<serde_yaml::Value>(rules)?;
-        // let data = PyOrganizeConfig::deserialize_from_value(value.into()).unwrap();
+        let conversion = PyOrganizeConfig::load_from_string(rules)?.convert();
+
+        assert!(conversion.issues.is_empty(), "{:?}", conversion.issues);
+        assert_eq!(conversion.config.rules().len(), 2);
 
         Ok(())
     }
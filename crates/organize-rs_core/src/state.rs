@@ -1,10 +1,15 @@
 // TODO: Implement typestate for running organize
 // https://cliffle.com/blog/rust-typestate/
 
-use jwalk::DirEntry;
+use std::path::PathBuf;
+
+use console::style;
 
 use crate::{
-    actions::conflicts::ConflictResolutionKind, actors::location_walker::DirEntryData, rules::Rule,
+    actions::{conflicts::ConflictResolutions, ActionResultKind},
+    actors::{conflict_handler::DetectedConflict, location_walker::DirEntryData},
+    error::OrganizeResult,
+    rules::Rule,
 };
 
 // States
@@ -22,7 +27,7 @@ pub struct Inspection {
 #[derive(Debug, Default)]
 pub struct ConflictHandling {
     entries: Vec<(Rule, DirEntryData)>,
-    conflicts: Vec<DirEntry<((), ())>>,
+    conflicts: Vec<DetectedConflict>,
 }
 
 impl Inspection {
@@ -43,11 +48,19 @@ impl Inspection {
 }
 
 impl ConflictHandling {
-    pub fn with_entries(entries: Vec<(Rule, DirEntryData)>) -> Self {
-        Self {
-            entries,
-            conflicts: vec![],
-        }
+    pub fn with_conflicts(
+        entries: Vec<(Rule, DirEntryData)>,
+        conflicts: Vec<DetectedConflict>,
+    ) -> Self {
+        Self { entries, conflicts }
+    }
+
+    pub fn conflicts(&self) -> &[DetectedConflict] {
+        &self.conflicts
+    }
+
+    pub fn entries(self) -> Vec<(Rule, DirEntryData)> {
+        self.entries
     }
 }
 
@@ -57,16 +70,23 @@ pub struct AskConfirmation;
 #[derive(Debug, Default)]
 pub struct ActionPreview {
     entries: Vec<(Rule, DirEntryData)>,
-    conflicts: Option<Vec<ConflictResolutionKind>>,
+    resolutions: ConflictResolutions,
 }
 
 impl ActionPreview {
     pub fn with_entries(entries: Vec<(Rule, DirEntryData)>) -> Self {
         Self {
             entries,
-            conflicts: None,
+            resolutions: vec![],
         }
     }
+    pub fn with_resolutions(mut self, resolutions: ConflictResolutions) -> Self {
+        self.resolutions = resolutions;
+        self
+    }
+    pub fn resolutions(&self) -> &ConflictResolutions {
+        &self.resolutions
+    }
     pub fn entries(self) -> Vec<(Rule, DirEntryData)> {
         self.entries
     }
@@ -75,23 +95,99 @@ impl ActionPreview {
 #[derive(Debug, Default)]
 pub struct ActionApplication {
     entries: Vec<(Rule, DirEntryData)>,
-    conflicts: Option<Vec<ConflictResolutionKind>>,
+    resolutions: ConflictResolutions,
 }
 
 impl ActionApplication {
     pub fn with_entries(entries: Vec<(Rule, DirEntryData)>) -> Self {
         Self {
             entries,
-            conflicts: None,
+            resolutions: vec![],
         }
     }
+    pub fn with_resolutions(mut self, resolutions: ConflictResolutions) -> Self {
+        self.resolutions = resolutions;
+        self
+    }
+    pub fn resolutions(&self) -> &ConflictResolutions {
+        &self.resolutions
+    }
     pub fn entries(self) -> Vec<(Rule, DirEntryData)> {
         self.entries
     }
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Reporting;
+#[derive(Debug, Default)]
+pub struct Reporting {
+    outcomes: Vec<(PathBuf, OrganizeResult<ActionResultKind>)>,
+}
+
+impl Reporting {
+    pub fn with_outcomes(outcomes: Vec<(PathBuf, OrganizeResult<ActionResultKind>)>) -> Self {
+        Self { outcomes }
+    }
+
+    pub fn outcomes(&self) -> &[(PathBuf, OrganizeResult<ActionResultKind>)] {
+        &self.outcomes
+    }
+
+    pub fn successful_count(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| match outcome {
+                Ok(ActionResultKind::Successful) => true,
+                Ok(ActionResultKind::PerItem { failed, .. }) => failed.is_empty(),
+                _ => false,
+            })
+            .count()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| match outcome {
+                Err(_) => true,
+                Ok(ActionResultKind::PerItem { failed, .. }) => !failed.is_empty(),
+                _ => false,
+            })
+            .count()
+    }
+
+    pub fn conflicted_count(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, Ok(ActionResultKind::Conflicted(_))))
+            .count()
+    }
+
+    pub fn print_report(&self) {
+        self.outcomes.iter().for_each(|(path, outcome)| match outcome {
+            Ok(ActionResultKind::Conflicted(conflict)) => println!(
+                "{} {conflict}: '{}'",
+                style("(Conflict)").yellow(),
+                path.display()
+            ),
+            Ok(ActionResultKind::Skipped) => {
+                println!("{} '{}'", style("(Skipped)").blue(), path.display())
+            }
+            Err(err) => eprintln!("{} '{}': {err}", style("(Failed)").red(), path.display()),
+            Ok(ActionResultKind::PerItem { failed, .. }) => {
+                for (item, err) in failed {
+                    eprintln!("{} '{}': {err}", style("(Failed)").red(), item.display());
+                }
+            }
+            Ok(ActionResultKind::Preview { .. } | ActionResultKind::Successful) => (),
+        });
+
+        println!(
+            "Successful: {}, Conflicts: {}, Failed: {}, Total: {}",
+            self.successful_count(),
+            self.conflicted_count(),
+            self.failed_count(),
+            self.outcomes.len()
+        );
+    }
+}
 
 pub trait ProcessingStage {}
 
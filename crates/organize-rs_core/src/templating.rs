@@ -1,9 +1,16 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr, time::SystemTime};
 
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local, NaiveDateTime, TimeZone,
+};
+use jwalk::{ClientState, DirEntry};
 use winnow::error::Error;
 
-use crate::parsers::template::{
-    parse_dotted_template, parse_strftime_template, parse_transform_template,
+use crate::{
+    counters::COUNTER_KEY,
+    error::{OrganizeResult, TemplateErrorKind},
+    parsers::template::{parse_dotted_template, parse_strftime_template, parse_transform_template},
 };
 use aho_corasick::{AhoCorasick, PatternID};
 
@@ -39,6 +46,82 @@ pub enum TransformationKind {
     DateTime,
 }
 
+impl TransformationKind {
+    /// Applies the transformation to an already rendered `value`
+    ///
+    /// # Errors
+    ///
+    /// If the transformation needs a format, e.g. `strftime`
+    pub fn apply(&self, value: String) -> OrganizeResult<String> {
+        let words = || split_words(&value);
+
+        match self {
+            TransformationKind::UpperCase => Ok(value.to_uppercase()),
+            TransformationKind::LowerCase => Ok(value.to_lowercase()),
+            TransformationKind::CamelCase => Ok(words()
+                .iter()
+                .enumerate()
+                .map(|(index, word)| match index {
+                    0 => word.to_lowercase(),
+                    _ => capitalize(word),
+                })
+                .collect()),
+            TransformationKind::SnakeCase => Ok(words()
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_")),
+            TransformationKind::KebabCase => Ok(words()
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("-")),
+            TransformationKind::DateTime => {
+                Err(TemplateErrorKind::NotSupported(format!("{self:?} without a format")).into())
+            }
+        }
+    }
+}
+
+/// Splits `value` into words at non-alphanumeric characters and where a
+/// lowercase letter is followed by an uppercase one, e.g. `myFile-name 2`
+/// into `my`, `File`, `name` and `2`
+fn split_words(value: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+
+    for c in value.chars() {
+        if !c.is_alphanumeric() {
+            previous_lowercase = false;
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_numeric();
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect()
+    })
+}
+
 impl FromStr for TransformationKind {
     type Err = String;
 
@@ -74,6 +157,18 @@ pub enum DateAttributeArgKind {
     NotSet,
 }
 
+impl DateAttributeArgKind {
+    /// `strftime` format string for the date attribute
+    pub fn format_str(&self) -> &'static str {
+        match self {
+            DateAttributeArgKind::Year => "%Y",
+            DateAttributeArgKind::Month => "%m",
+            DateAttributeArgKind::Day => "%d",
+            DateAttributeArgKind::NotSet => "%Y-%m-%d",
+        }
+    }
+}
+
 impl Default for DateAttributeArgKind {
     fn default() -> Self {
         Self::NotSet
@@ -88,7 +183,7 @@ impl FromStr for DateAttributeArgKind {
             "year" => Self::Year,
             "month" => Self::Month,
             "day" => Self::Day,
-            _ => Self::default(),
+            _ => return Err(format!("date attribute `{s}` not recognized.")),
         };
 
         Ok(result)
@@ -107,10 +202,117 @@ pub enum TemplateFeatureKind {
     Utility(UtilityKind),
     MetaData(MetaDataKind),
     Content(String),
+    /// named capture group of a regex filter
+    Regex(String),
+    /// original of an item found by the duplicate filter
+    DuplicateOriginal,
+    /// EXIF metadata of an image, e.g. `model` or `datetime.year`
+    Exif(String),
     // TODO: Aliases
     NotRecognized,
 }
 
+impl TemplateFeatureKind {
+    /// Returns the value of the feature for the item at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the feature is not supported (yet) or the value is not
+    /// available for the item
+    pub fn value(&self, path: &Path, context: &TemplateContext) -> OrganizeResult<String> {
+        let value = match self {
+            TemplateFeatureKind::Utility(UtilityKind::Counter) => context
+                .counter
+                .map(|counter| counter.to_string())
+                .or_else(|| context.value(COUNTER_KEY).cloned()),
+            TemplateFeatureKind::Regex(name) => context.value(&format!("regex.{name}")).cloned(),
+            TemplateFeatureKind::Content(name) => {
+                context.value(&format!("content.{name}")).cloned()
+            }
+            TemplateFeatureKind::DuplicateOriginal => context.value("duplicate.original").cloned(),
+            TemplateFeatureKind::Exif(key) => context.value(&format!("exif.{key}")).cloned(),
+            TemplateFeatureKind::MetaData(MetaDataKind::Name) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string()),
+            TemplateFeatureKind::MetaData(MetaDataKind::Extension) => path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string()),
+            TemplateFeatureKind::MetaData(MetaDataKind::Created(arg)) => path
+                .metadata()?
+                .created()
+                .ok()
+                .map(|time| format_system_time(time, arg.format_str())),
+            TemplateFeatureKind::MetaData(MetaDataKind::LastModified(arg)) => path
+                .metadata()?
+                .modified()
+                .ok()
+                .map(|time| format_system_time(time, arg.format_str())),
+            _ => return Err(TemplateErrorKind::NotSupported(format!("{self:?}")).into()),
+        };
+
+        value.ok_or_else(|| {
+            TemplateErrorKind::ValueNotAvailable {
+                template: format!("{self:?}"),
+                path: path.to_path_buf(),
+            }
+            .into()
+        })
+    }
+
+    /// Returns the point in time of a date feature for the item at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the feature is not a date or the date is not available for the item
+    pub fn date_time(
+        &self,
+        path: &Path,
+        context: &TemplateContext,
+    ) -> OrganizeResult<DateTime<Local>> {
+        let date_time = match self {
+            TemplateFeatureKind::MetaData(MetaDataKind::Created(_)) => {
+                path.metadata()?.created().ok().map(DateTime::<Local>::from)
+            }
+            TemplateFeatureKind::MetaData(MetaDataKind::LastModified(_)) => path
+                .metadata()?
+                .modified()
+                .ok()
+                .map(DateTime::<Local>::from),
+            TemplateFeatureKind::Exif(key) if key == "datetime" => context
+                .value("exif.datetime")
+                .and_then(|value| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok())
+                .and_then(|date_time| Local.from_local_datetime(&date_time).earliest()),
+            _ => return Err(TemplateErrorKind::NotSupported(format!("{self:?} as date")).into()),
+        };
+
+        date_time.ok_or_else(|| {
+            TemplateErrorKind::ValueNotAvailable {
+                template: format!("{self:?}"),
+                path: path.to_path_buf(),
+            }
+            .into()
+        })
+    }
+}
+
+fn format_system_time(time: SystemTime, format: &str) -> String {
+    DateTime::<Local>::from(time).format(format).to_string()
+}
+
+/// Formats `date_time` with a `strftime` format string
+///
+/// # Errors
+///
+/// If the format string contains invalid specifiers
+fn format_date_time(date_time: &DateTime<Local>, format: &str) -> OrganizeResult<String> {
+    let items = StrftimeItems::new(format).collect::<Vec<_>>();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(TemplateErrorKind::InvalidFormat(format.to_string()).into());
+    }
+
+    Ok(date_time.format_with_items(items.into_iter()).to_string())
+}
+
 impl From<&[&str]> for TemplateFeatureKind {
     fn from(value: &[&str]) -> Self {
         match value {
@@ -119,23 +321,29 @@ impl From<&[&str]> for TemplateFeatureKind {
             ["metadata", "date_added"] => {
                 Self::MetaData(MetaDataKind::DateAdded(DateAttributeArgKind::NotSet))
             }
-            ["metadata", "date_added", date] => Self::MetaData(MetaDataKind::DateAdded(
-                DateAttributeArgKind::from_str(date).expect("conversion to date shouldn't fail"),
-            )),
+            ["metadata", "date_added", date] => DateAttributeArgKind::from_str(date)
+                .map_or(Self::NotRecognized, |date| {
+                    Self::MetaData(MetaDataKind::DateAdded(date))
+                }),
             ["metadata", "created"] => {
                 Self::MetaData(MetaDataKind::Created(DateAttributeArgKind::NotSet))
             }
-            ["metadata", "created", date] => Self::MetaData(MetaDataKind::Created(
-                DateAttributeArgKind::from_str(date).expect("conversion to date shouldn't fail"),
-            )),
+            ["metadata", "created", date] => DateAttributeArgKind::from_str(date)
+                .map_or(Self::NotRecognized, |date| {
+                    Self::MetaData(MetaDataKind::Created(date))
+                }),
             ["metadata", "last_modified"] => {
                 Self::MetaData(MetaDataKind::LastModified(DateAttributeArgKind::NotSet))
             }
-            ["metadata", "last_modified", date] => Self::MetaData(MetaDataKind::LastModified(
-                DateAttributeArgKind::from_str(date).expect("conversion to date shouldn't fail"),
-            )),
+            ["metadata", "last_modified", date] => DateAttributeArgKind::from_str(date)
+                .map_or(Self::NotRecognized, |date| {
+                    Self::MetaData(MetaDataKind::LastModified(date))
+                }),
             ["metadata", "name"] => Self::MetaData(MetaDataKind::Name),
             ["content", last] => Self::Content(last.to_string()),
+            ["regex", name] => Self::Regex(name.to_string()),
+            ["duplicate", "original"] => Self::DuplicateOriginal,
+            ["exif", key @ ..] if !key.is_empty() => Self::Exif(key.join(".")),
             _ => Self::NotRecognized,
         }
     }
@@ -159,21 +367,158 @@ pub enum TemplateKind {
     Uninitialized,
 }
 
+/// Values extracted from an item while filtering it, keyed by the
+/// dotted name templates refer to them with, e.g. `regex.invoice_no`
+pub type TemplateValues = HashMap<String, String>;
+
+/// Values templates can refer to, that can't be derived from the item itself
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    counter: Option<usize>,
+    values: TemplateValues,
+}
+
+impl TemplateContext {
+    /// Sets the value for `{utility.counter}`, it takes precedence over
+    /// the number assigned to the item by its rule's counter
+    #[must_use]
+    pub fn with_counter(mut self, counter: usize) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Sets the values the filters extracted from the item
+    #[must_use]
+    pub fn with_values(mut self, values: TemplateValues) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Sets a single value, e.g. the number [`CounterSettings`] assigned to
+    /// the item
+    ///
+    /// [`CounterSettings`]: crate::counters::CounterSettings
+    pub(crate) fn insert_value(&mut self, key: &str, value: String) {
+        _ = self.values.insert(key.to_string(), value);
+    }
+
+    fn value(&self, key: &str) -> Option<&String> {
+        self.values.get(key)
+    }
+}
+
 impl TemplateKind {
-    pub fn get_template(&self) -> String {
+    /// The feature the template refers to
+    pub fn data(&self) -> Option<&TemplateFeatureKind> {
         match self {
-            TemplateKind::Dotted { data: _ } => todo!(),
+            TemplateKind::Dotted { data }
+            | TemplateKind::Transformation { data, .. }
+            | TemplateKind::FormattedTransformation { data, .. } => Some(data),
+            TemplateKind::Uninitialized => None,
+        }
+    }
+
+    /// Renders the template for the item at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the template is not supported (yet) or the value is not
+    /// available for the item
+    pub fn render(&self, path: &Path, context: &TemplateContext) -> OrganizeResult<String> {
+        match self {
+            TemplateKind::Dotted { data } => data.value(path, context),
+            TemplateKind::Transformation { kind, data } => kind.apply(data.value(path, context)?),
             TemplateKind::FormattedTransformation {
-                kind: _,
-                data: _,
-                format: _,
-            } => todo!(),
-            TemplateKind::Transformation { kind: _, data: _ } => todo!(),
-            TemplateKind::Uninitialized => todo!(),
+                kind: TransformationKind::DateTime,
+                data,
+                format,
+            } => format_date_time(&data.date_time(path, context)?, format),
+            TemplateKind::FormattedTransformation { .. } | TemplateKind::Uninitialized => {
+                Err(TemplateErrorKind::NotSupported(format!("{self:?}")).into())
+            }
         }
     }
 }
 
+/// Renders all templates contained in `input` for the item at `path`
+///
+/// Text outside of templates is kept as is, e.g.
+/// `Archive/{metadata.last_modified.year}/` is rendered to `Archive/2023/`.
+///
+/// # Errors
+///
+/// If a template can't be parsed or rendered
+pub fn render_templates(
+    input: &str,
+    path: &Path,
+    context: &TemplateContext,
+) -> OrganizeResult<String> {
+    let mut rendered = String::with_capacity(input.len());
+    let mut rest = input;
+
+    for template in find_templates(input)? {
+        let (before, after) = rest
+            .split_once(template)
+            .expect("templates are found in order");
+        rendered.push_str(before);
+
+        let kind = TemplateKind::from_str(template)
+            .map_err(|_| TemplateErrorKind::InvalidTemplate(template.to_string()))?;
+        if kind.data() == Some(&TemplateFeatureKind::NotRecognized) {
+            return Err(TemplateErrorKind::UnknownKey(template.to_string()).into());
+        }
+        rendered.push_str(&kind.render(path, context)?);
+
+        rest = after;
+    }
+
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Renders all templates contained in `input` for a walked `entry`, see
+/// [`render_templates`]
+///
+/// # Errors
+///
+/// If a template can't be parsed or rendered
+pub fn render_entry_templates<C: ClientState>(
+    input: &str,
+    entry: &DirEntry<C>,
+    context: &TemplateContext,
+) -> OrganizeResult<String> {
+    render_templates(input, &entry.path(), context)
+}
+
+/// Returns all templates contained in `input`, including their braces
+///
+/// # Errors
+///
+/// If a template is missing its closing brace
+pub fn find_templates(input: &str) -> OrganizeResult<Vec<&str>> {
+    let mut templates = vec![];
+    let mut rest = input;
+
+    while let Some(start) = rest.find('{') {
+        let template_start = &rest[start..];
+        let Some(end) = template_start.find('}') else {
+            return Err(TemplateErrorKind::UnclosedTemplate(input.to_string()).into());
+        };
+        // also consume doubled closing braces, e.g. `{{metadata.name}}`
+        let end = end
+            + template_start[end..]
+                .chars()
+                .take_while(|c| *c == '}')
+                .count();
+
+        templates.push(&template_start[..end]);
+        rest = &template_start[end..];
+    }
+
+    Ok(templates)
+}
+
 impl FromStr for TemplateKind {
     type Err = Error<String>;
 
@@ -368,4 +713,231 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_render_templates_with_literal_text_passes() {
+        let path = PathBuf::from("Invoice.pdf");
+        let context = TemplateContext::default().with_counter(3);
+
+        let rendered = render_templates(
+            "PDFs/{uppercase(metadata.extension)}/{lowercase(metadata.name)}_{utility.counter}",
+            &path,
+            &context,
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "PDFs/PDF/invoice_3");
+    }
+
+    #[test]
+    fn test_render_templates_with_regex_values_passes() {
+        let path = PathBuf::from("RG123-sig.pdf");
+        let context = TemplateContext::default().with_values(TemplateValues::from([(
+            "regex.invoice_no2".to_string(),
+            "123".to_string(),
+        )]));
+
+        let rendered = render_templates(
+            "Invoices/{regex.invoice_no2}.{metadata.extension}",
+            &path,
+            &context,
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "Invoices/123.pdf");
+    }
+
+    #[test]
+    fn test_render_templates_with_exif_values_passes() {
+        let path = PathBuf::from("IMG_0001.jpg");
+        let context = TemplateContext::default().with_values(TemplateValues::from([
+            ("exif.datetime.year".to_string(), "2021".to_string()),
+            ("exif.model".to_string(), "Pixel 7".to_string()),
+        ]));
+
+        let rendered =
+            render_templates("{exif.datetime.year}/{exif.model}/", &path, &context).unwrap();
+
+        assert_eq!(rendered, "2021/Pixel 7/");
+    }
+
+    #[test]
+    fn test_render_templates_with_missing_regex_value_fails() {
+        let path = PathBuf::from("invoice.pdf");
+
+        let result = render_templates("{regex.invoice_no}", &path, &TemplateContext::default());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_templates_with_last_modified_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "").unwrap();
+        let mtime = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(&path, mtime).unwrap();
+
+        let rendered = render_templates(
+            "Archive/{metadata.last_modified.year}/",
+            &path,
+            &TemplateContext::default(),
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "Archive/2020/");
+    }
+
+    #[test]
+    fn test_render_templates_with_unknown_key_fails() {
+        let path = PathBuf::from("test.txt");
+
+        let result = render_templates("{metadata.unknown}", &path, &TemplateContext::default());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_templates_with_unclosed_template_fails() {
+        let path = PathBuf::from("test.txt");
+
+        let result = render_templates("{metadata.name", &path, &TemplateContext::default());
+
+        assert!(result.is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("{camelcase(metadata.name)}", "myHolidayPhotos2023")]
+    #[case("{snakecase(metadata.name)}", "my_holiday_photos_2023")]
+    #[case("{kebabcase(metadata.name)}", "my-holiday-photos-2023")]
+    #[case("{uppercase(metadata.name)}", "MY HOLIDAY-PHOTOS_2023")]
+    fn test_render_case_transformations_passes(#[case] template: &str, #[case] expected: &str) {
+        let path = PathBuf::from("my Holiday-photos_2023.jpg");
+
+        let rendered = render_templates(template, &path, &TemplateContext::default()).unwrap();
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_split_words_at_case_changes_passes() {
+        assert_eq!(split_words("myFileName"), ["my", "File", "Name"]);
+        assert_eq!(split_words("HTTPServer v2"), ["HTTPServer", "v2"]);
+    }
+
+    #[test]
+    fn test_render_strftime_of_last_modified_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "").unwrap();
+        // noon, so the date is the same in all time zones
+        let mtime =
+            filetime::FileTime::from_unix_time(1_600_000_000 - 1_600_000_000 % 86_400 + 43_200, 0);
+        filetime::set_file_mtime(&path, mtime).unwrap();
+
+        let rendered = render_templates(
+            "Archive/{strftime(metadata.last_modified, '%Y-%m')}/{metadata.name}",
+            &path,
+            &TemplateContext::default(),
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "Archive/2020-09/test");
+    }
+
+    #[test]
+    fn test_render_strftime_of_exif_datetime_passes() {
+        let path = PathBuf::from("IMG_0001.jpg");
+        let context = TemplateContext::default().with_values(TemplateValues::from([(
+            "exif.datetime".to_string(),
+            "2021-07-14 09:30:00".to_string(),
+        )]));
+
+        let rendered = render_templates(
+            "{strftime(exif.datetime, '%d.%m.%Y %H:%M')}",
+            &path,
+            &context,
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "14.07.2021 09:30");
+    }
+
+    #[test]
+    fn test_render_strftime_with_invalid_format_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let err = render_templates(
+            "{strftime(metadata.last_modified, '%Q')}",
+            &path,
+            &TemplateContext::default(),
+        )
+        .unwrap_err()
+        .into_inner();
+
+        assert!(matches!(
+            err,
+            crate::error::OrganizeErrorKind::Template(TemplateErrorKind::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_strftime_of_non_date_fails() {
+        let path = PathBuf::from("test.txt");
+
+        let result = render_templates(
+            "{strftime(metadata.name, '%Y')}",
+            &path,
+            &TemplateContext::default(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parsing_unknown_date_attribute_is_not_recognized_passes() {
+        let template_kind = TemplateKind::from_str("{metadata.created.week}").unwrap();
+
+        assert_eq!(
+            template_kind.data(),
+            Some(&TemplateFeatureKind::NotRecognized)
+        );
+    }
+
+    #[test]
+    fn test_render_templates_with_content_values_passes() {
+        let path = PathBuf::from("invoice.pdf");
+        let context = TemplateContext::default().with_values(TemplateValues::from([(
+            "content.customer".to_string(),
+            "Acme".to_string(),
+        )]));
+
+        let rendered =
+            render_templates("{content.customer}/{metadata.name}", &path, &context).unwrap();
+
+        assert_eq!(rendered, "Acme/invoice");
+    }
+
+    #[test]
+    fn test_render_entry_templates_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("report.txt"), "").unwrap();
+        let entry = jwalk::WalkDir::new(dir.path())
+            .into_iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name() == "report.txt")
+            .unwrap();
+        let context = TemplateContext::default().with_counter(2);
+
+        let rendered = render_entry_templates(
+            "{uppercase(metadata.extension)}/{metadata.name}_{utility.counter}",
+            &entry,
+            &context,
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "TXT/report_2");
+    }
 }
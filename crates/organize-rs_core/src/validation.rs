@@ -0,0 +1,459 @@
+//! Semantic validation of configs
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use displaydoc::Display;
+use regex::Regex;
+
+use crate::{
+    actions::{conflicts::ConflictResolutionKind, ActionApplicationKind, ActionKind},
+    config::OrganizeConfig,
+    filesystem::expand_path,
+    filters::{script::ScriptFilter, DuplicateKind, FilterKind},
+    rules::Rule,
+    templating::{find_templates, TemplateFeatureKind, TemplateKind},
+};
+
+/// How severe a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+pub enum SeverityKind {
+    /// info
+    Info,
+    /// warning
+    Warning,
+    /// error
+    Error,
+}
+
+/// A finding of the [`ConfigValidator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// how severe the finding is
+    pub severity: SeverityKind,
+    /// name of the rule the finding belongs to
+    pub rule: String,
+    /// description of the finding
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Returns `true` if the config can't be run as it is
+    pub fn is_error(&self) -> bool {
+        self.severity == SeverityKind::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: rule `{}`: {}",
+            self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Checks a config for mistakes, that parsing it doesn't reveal
+///
+/// Locations need to exist, ranges need to be able to match anything,
+/// templates need to be valid and refer to defined aliases and dangerous
+/// or ambiguous settings are pointed out.
+#[derive(Debug)]
+pub struct ConfigValidator<'a> {
+    config: &'a OrganizeConfig,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> ConfigValidator<'a> {
+    pub fn new(config: &'a OrganizeConfig) -> Self {
+        Self {
+            config,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Validates all rules and returns the findings in the order of the rules
+    pub fn validate(mut self) -> Vec<Diagnostic> {
+        for rule in self.config.rules().iter() {
+            self.check_locations(rule);
+            self.check_filters(rule);
+            self.check_actions(rule);
+            self.check_counter(rule);
+        }
+
+        self.diagnostics
+    }
+
+    fn report(&mut self, severity: SeverityKind, rule: &Rule, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            rule: rule.name().to_string(),
+            message,
+        });
+    }
+
+    fn check_locations(&mut self, rule: &Rule) {
+        for location in rule.locations().iter() {
+            let path = location.path().to_string_lossy();
+            let has_templates = self.check_templates(rule, &path, "location");
+            if has_templates {
+                continue;
+            }
+
+            match expand_path(location.path()) {
+                Ok(expanded) if !expanded.exists() => self.report(
+                    SeverityKind::Error,
+                    rule,
+                    format!("location `{path}` doesn't exist"),
+                ),
+                Ok(_) => (),
+                Err(err) => self.report(SeverityKind::Error, rule, format!("location: {err}")),
+            }
+        }
+    }
+
+    fn check_filters(&mut self, rule: &Rule) {
+        for filter_group in rule.filters().iter() {
+            for filter in filter_group.filters().iter() {
+                match filter {
+                    FilterKind::AllItems {
+                        i_agree_it_is_dangerous: false,
+                    } => self.report(
+                        SeverityKind::Error,
+                        rule,
+                        "`all_items` matches every item, set `i_agree_it_is_dangerous: true` \
+                         to confirm"
+                            .to_string(),
+                    ),
+                    FilterKind::Size { range: Some(range) } if range.is_empty() => self.report(
+                        SeverityKind::Error,
+                        rule,
+                        format!("`size` range {range} can never match"),
+                    ),
+                    FilterKind::Created { range: Some(range) }
+                    | FilterKind::LastAccessed { range: Some(range) }
+                    | FilterKind::LastModified { range: Some(range) }
+                        if range.is_empty() =>
+                    {
+                        self.report(
+                            SeverityKind::Error,
+                            rule,
+                            format!("period range {range} can never match"),
+                        )
+                    }
+                    FilterKind::Duplicate {
+                        detect_original_by: DuplicateKind::Hash,
+                        ..
+                    } => self.report(
+                        SeverityKind::Error,
+                        rule,
+                        "`detect_original_by: hash` can't tell the original apart, as all \
+                         duplicates share their hash, use `first_seen`, `name`, `created` or \
+                         `last_modified`"
+                            .to_string(),
+                    ),
+                    FilterKind::Regex { expr } | FilterKind::FileContent { expr } => {
+                        if let Err(err) = Regex::new(expr) {
+                            self.report(
+                                SeverityKind::Error,
+                                rule,
+                                format!("invalid regular expression `{expr}`: {err}"),
+                            );
+                        }
+                    }
+                    FilterKind::Script { source, path } => {
+                        if let Err(err) = ScriptFilter::compile(source.as_deref(), path.as_deref())
+                        {
+                            self.report(SeverityKind::Error, rule, format!("script: {err}"));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn check_actions(&mut self, rule: &Rule) {
+        let mut destructive_count = 0;
+
+        for container in rule.actions().iter() {
+            for (text, name) in action_templates(&container.action) {
+                _ = self.check_templates(rule, text, name);
+            }
+
+            let is_destructive = matches!(container.mode, ActionApplicationKind::Destructive)
+                && !matches!(
+                    container.action,
+                    ActionKind::NoAction | ActionKind::Echo { .. } | ActionKind::Confirm { .. }
+                );
+            if !is_destructive {
+                continue;
+            }
+            destructive_count += 1;
+
+            if let Some((strategy, rename_template)) = container.action.conflict_resolution() {
+                self.check_conflict_resolution(rule, strategy, rename_template);
+            }
+        }
+
+        if destructive_count > 1 {
+            self.report(
+                SeverityKind::Warning,
+                rule,
+                format!(
+                    "rule has {destructive_count} destructive actions, later actions might \
+                     not find the items at their original location anymore"
+                ),
+            );
+        }
+    }
+
+    fn check_counter(&mut self, rule: &Rule) {
+        if rule.counter().step == 0 {
+            self.report(
+                SeverityKind::Error,
+                rule,
+                "counter `step` must be greater than 0".to_string(),
+            );
+        }
+    }
+
+    fn check_conflict_resolution(
+        &mut self,
+        rule: &Rule,
+        strategy: &ConflictResolutionKind,
+        rename_template: Option<&[String]>,
+    ) {
+        let renames = matches!(
+            strategy,
+            ConflictResolutionKind::RenameNew
+                | ConflictResolutionKind::RenameExisting
+                | ConflictResolutionKind::MoveToFolder { .. }
+        );
+
+        match strategy {
+            ConflictResolutionKind::Skip => self.report(
+                SeverityKind::Info,
+                rule,
+                "destructive action skips items with an existing destination, set \
+                 `on_conflict` to resolve conflicts otherwise"
+                    .to_string(),
+            ),
+            ConflictResolutionKind::MoveToFolder { path }
+                if !expand_path(path).is_ok_and(|path| path.exists()) =>
+            {
+                self.report(
+                    SeverityKind::Warning,
+                    rule,
+                    format!(
+                        "folder `{}` to move conflicting items to doesn't exist",
+                        path.display()
+                    ),
+                )
+            }
+            _ => (),
+        }
+
+        if !renames && rename_template.is_some() {
+            self.report(
+                SeverityKind::Warning,
+                rule,
+                "`rename_template` is ignored, as `on_conflict` doesn't rename items".to_string(),
+            );
+        }
+    }
+
+    /// Reports invalid templates in `text` and returns `true` if it contains any
+    fn check_templates(&mut self, rule: &Rule, text: &str, name: &str) -> bool {
+        let templates = match find_templates(text) {
+            Ok(templates) => templates,
+            Err(err) => {
+                self.report(SeverityKind::Error, rule, format!("{name}: {err}"));
+                return true;
+            }
+        };
+
+        for template in &templates {
+            let content = template.trim_matches(|c: char| c == '{' || c == '}' || c == ' ');
+
+            if let Some(alias) = content.strip_prefix("alias.") {
+                if self.config.aliases().get(alias).is_none() {
+                    self.report(
+                        SeverityKind::Error,
+                        rule,
+                        format!("{name}: alias `{alias}` in `{template}` is not defined"),
+                    );
+                }
+                continue;
+            }
+
+            match TemplateKind::from_str(template) {
+                Ok(
+                    TemplateKind::Dotted { data }
+                    | TemplateKind::Transformation { data, .. }
+                    | TemplateKind::FormattedTransformation { data, .. },
+                ) if data != TemplateFeatureKind::NotRecognized => (),
+                _ => self.report(
+                    SeverityKind::Error,
+                    rule,
+                    format!("{name}: `{template}` is not a valid template"),
+                ),
+            }
+        }
+
+        !templates.is_empty()
+    }
+}
+
+/// The texts of an action that can contain templates and their names
+fn action_templates(action: &ActionKind) -> Vec<(&str, &'static str)> {
+    fn path(path: &Path) -> &str {
+        path.to_str().unwrap_or_default()
+    }
+
+    fn rename_templates(templates: &Option<Vec<String>>) -> Vec<(&str, &'static str)> {
+        templates
+            .iter()
+            .flatten()
+            .map(|template| (template.as_str(), "rename_template"))
+            .collect()
+    }
+
+    match action {
+        ActionKind::Confirm { msg: Some(msg), .. } => vec![(msg.as_str(), "msg")],
+        ActionKind::Copy {
+            dst,
+            rename_template,
+            ..
+        }
+        | ActionKind::Move {
+            dst,
+            rename_template,
+            ..
+        } => {
+            let mut texts = vec![(path(dst), "dst")];
+            texts.extend(rename_templates(rename_template));
+            texts
+        }
+        ActionKind::Rename {
+            name,
+            rename_template,
+            ..
+        } => {
+            let mut texts = vec![(name.as_str(), "name")];
+            texts.extend(rename_templates(rename_template));
+            texts
+        }
+        ActionKind::Echo { msg } => vec![(msg.as_str(), "msg")],
+        ActionKind::Symlink { dst } => vec![(path(dst), "dst")],
+        ActionKind::Write { txt, file, .. } => vec![(txt.as_str(), "txt"), (path(file), "file")],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigFileFormat;
+
+    use super::*;
+
+    fn validate(yaml: &str) -> Vec<Diagnostic> {
+        let config = OrganizeConfig::load_from_string(yaml, ConfigFileFormat::Yaml);
+        ConfigValidator::new(&config).validate()
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_valid_config_passes() {
+        let diagnostics = validate(
+            r#"
+aliases:
+  - name: downloads
+    kind: folder
+    items:
+      - ~/Downloads/
+rules:
+  - name: Sort
+    locations:
+      - !default_settings "{alias.downloads}"
+    filter_groups:
+      - filters:
+          - !size
+            range: 1KB..1MB
+        results: include
+        match: all
+    actions:
+      - mode: destructive
+        action: !move
+          dst: ~/Sorted/{uppercase(metadata.extension)}/
+          on_conflict: rename_new
+"#,
+        );
+
+        assert!(diagnostics.is_empty(), "{diagnostics:#?}");
+    }
+
+    #[test]
+    fn test_validate_invalid_config_fails() {
+        let diagnostics = validate(
+            r#"
+rules:
+  - name: Everything
+    locations:
+      - !default_settings /does/not/exist/for/organize
+      - !default_settings "{alias.missing}"
+    filter_groups:
+      - filters:
+          - !all_items
+            i_agree_it_is_dangerous: false
+          - !size
+            range: ..0B
+          - !script
+            source: "entry.size >"
+          - !regex
+            expr: "^(invoice"
+          - !file_content
+            expr: "total: ["
+          - !duplicate
+            detect_original_by: hash
+        results: include
+        match: all
+    actions:
+      - mode: destructive
+        action: !copy
+          dst: ~/Backup/{metadata.unknown}/
+          rename_template:
+            - "{metadata.name}_copy"
+      - mode: destructive
+        action: !trash
+    counter:
+      step: 0
+"#,
+        );
+
+        insta::assert_debug_snapshot!(messages(&diagnostics), @r###"
+        [
+            "error: rule `Everything`: location `/does/not/exist/for/organize` doesn't exist",
+            "error: rule `Everything`: location: alias `missing` in `{alias.missing}` is not defined",
+            "error: rule `Everything`: `all_items` matches every item, set `i_agree_it_is_dangerous: true` to confirm",
+            "error: rule `Everything`: `size` range SizeRange(1.0..0.0) can never match",
+            "error: rule `Everything`: script: script doesn't compile: Script is incomplete (line 1, position 13)",
+            "error: rule `Everything`: invalid regular expression `^(invoice`: regex parse error:\n    ^(invoice\n     ^\nerror: unclosed group",
+            "error: rule `Everything`: invalid regular expression `total: [`: regex parse error:\n    total: [\n           ^\nerror: unclosed character class",
+            "error: rule `Everything`: `detect_original_by: hash` can't tell the original apart, as all duplicates share their hash, use `first_seen`, `name`, `created` or `last_modified`",
+            "error: rule `Everything`: dst: `{metadata.unknown}` is not a valid template",
+            "info: rule `Everything`: destructive action skips items with an existing destination, set `on_conflict` to resolve conflicts otherwise",
+            "warning: rule `Everything`: `rename_template` is ignored, as `on_conflict` doesn't rename items",
+            "warning: rule `Everything`: rule has 2 destructive actions, later actions might not find the items at their original location anymore",
+            "error: rule `Everything`: counter `step` must be greater than 0",
+        ]
+        "###);
+        assert!(diagnostics.iter().any(Diagnostic::is_error));
+    }
+}
@@ -2,15 +2,17 @@
 
 pub mod action;
 mod check;
+mod convert;
 mod docs;
 pub mod filter;
 mod generate;
+mod migrate;
 mod run;
 
 use crate::{
     commands::{
-        action::ActionCmd, check::CheckCmd, docs::DocsCmd, filter::FilterCmd,
-        generate::GenerateCmd, run::RunCmd,
+        action::ActionCmd, check::CheckCmd, convert::ConvertCmd, docs::DocsCmd, filter::FilterCmd,
+        generate::GenerateCmd, migrate::MigrateCmd, run::RunCmd,
     },
     config::OrganizeAppConfig,
 };
@@ -42,12 +44,16 @@ pub enum OrganizeCmd {
     Action(ActionCmd),
     /// Check configs and scripts for errors
     Check(CheckCmd),
+    /// Convert configs of the Python version of organize
+    Convert(ConvertCmd),
     /// Show the documentation
     Docs(DocsCmd),
     /// Config snippets for filters that organize can apply
     Filter(FilterCmd),
     /// Generate completions for your shell, and organize config and script files
     Generate(GenerateCmd),
+    /// Upgrade configs written for older versions of organize
+    Migrate(MigrateCmd),
     /// Run predefined rules from configs or scripts
     Run(RunCmd),
 }
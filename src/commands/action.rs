@@ -1,24 +1,381 @@
 //! Actions that organize can apply
 
-use abscissa_core::{Command, Runnable};
+use std::{
+    io::{stdin, BufRead},
+    path::PathBuf,
+};
+
+use abscissa_core::{status_err, status_warn, Application, Command, Runnable, Shutdown};
+use anyhow::{bail, Result};
 use clap::Args;
-use organize_rs_core::actions::ActionKind;
+use serde::Serialize;
+
+use organize_rs_core::{
+    actions::{ActionApplicationKind, ActionContainer, ActionKind, ActionResultKind, WrittenFiles},
+    actors::{
+        conflict_handler::{ConflictHandler, DetectedConflict},
+        location_walker::DirEntryData,
+    },
+    config::{to_config_string, ConfigFileFormat},
+    counters::CounterSettings,
+    filesystem::expand_path,
+    rules::Rule,
+    state::Reporting,
+};
+
+use crate::application::ORGANIZE_APP;
+
+/// The actions, as they are pasted into a rule
+#[derive(Debug, Serialize)]
+struct ActionSnippet {
+    actions: Vec<ActionContainer>,
+}
 
 /// `action` subcommand
 #[derive(Command, Debug, Args, Clone)]
 pub struct ActionCmd {
     #[clap(subcommand)]
     action: ActionKind,
+
+    /// Paths of the items to apply the action to
+    #[arg(long, global = true, num_args = 1.., conflicts_with = "stdin")]
+    paths: Vec<PathBuf>,
+
+    /// Read the paths of the items from stdin, one per line
+    #[arg(long, global = true)]
+    stdin: bool,
+
+    /// Apply the action destructively, it is only previewed otherwise
+    #[arg(long, global = true)]
+    execute: bool,
+
+    /// Print the action as a config snippet instead of applying it
+    #[arg(long, global = true)]
+    print_config: bool,
+
+    /// Format of the config snippet
+    #[arg(long, global = true, default_value_t = ConfigFileFormat::Yaml, value_enum)]
+    format: ConfigFileFormat,
 }
 
 impl Runnable for ActionCmd {
     fn run(&self) {
-        // TODO: Support different config file formats
-        // ? implement `ActionKind::to_config_string(format: ConfigFileFormat)`
-        let yaml_string = serde_yaml::to_string(&self.action).unwrap();
-        println!("This is an 'action' snippet for a yaml config:");
+        if let Err(err) = self.inner_run() {
+            status_err!("{}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
+    }
+}
+
+impl ActionCmd {
+    fn inner_run(&self) -> Result<()> {
+        if self.print_config {
+            return self.print_config();
+        }
+
+        let paths = if self.stdin {
+            Self::read_paths(stdin().lock())?
+        } else if !self.paths.is_empty() {
+            self.paths.clone()
+        } else {
+            bail!("no paths given, pass them with `--paths` or `--stdin` or use `--print-config`");
+        };
+
+        let mut action = self.action.clone();
+        action.expand_paths()?;
+
+        let mode = if self.execute {
+            ActionApplicationKind::Destructive
+        } else {
+            ActionApplicationKind::Preview
+        };
+
+        let rule = Rule::builder()
+            .name("action")
+            .action(ActionContainer {
+                mode,
+                action: action.clone(),
+            })
+            .build();
+        let entries = [(rule, Self::entries(&paths)?)];
+
+        Self::check_conflicts(&ConflictHandler::new(&entries).detect_conflicts())?;
+
+        let [(_, data)] = entries;
+
+        self.apply(&action, &data)
+    }
+
+    /// Prints the conflicts, the action runs into
+    ///
+    /// # Errors
+    ///
+    /// If any conflict can't be resolved with the chosen `on_conflict`
+    /// resolution, as there is nobody to ask how to resolve it
+    fn check_conflicts(conflicts: &[DetectedConflict]) -> Result<()> {
+        for conflict in conflicts {
+            println!(
+                "(Conflict) {}: '{}' -> '{}' ({})",
+                conflict.kind,
+                conflict.path.display(),
+                conflict.destination.display(),
+                conflict.resolution
+            );
+        }
+
+        let unresolved_count = conflicts
+            .iter()
+            .filter(|conflict| conflict.is_unresolved())
+            .count();
+        if unresolved_count > 0 {
+            bail!(
+                "{unresolved_count} conflict(s) can't be resolved, choose another resolution with `--on-conflict`."
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Reads the paths from `reader`, one per line, empty lines are skipped
+    fn read_paths(reader: impl BufRead) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() {
+                paths.push(PathBuf::from(line));
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// The entries at `paths`, paths that don't exist are skipped with a warning
+    fn entries(paths: &[PathBuf]) -> Result<DirEntryData> {
+        let mut entries = vec![];
+
+        for path in paths {
+            let path = expand_path(path)?;
+            match jwalk::WalkDir::new(&path).into_iter().next() {
+                Some(Ok(entry)) => entries.push(entry),
+                Some(Err(err)) => status_warn!("skipping '{}': {}", path.display(), err),
+                None => status_warn!("skipping '{}': it doesn't exist", path.display()),
+            }
+        }
+
+        Ok(DirEntryData::from(entries))
+    }
+
+    /// Previews or applies `action` to the entries of `data`
+    ///
+    /// # Errors
+    ///
+    /// If the action fails to be previewed or applied for any of the entries
+    fn apply(&self, action: &ActionKind, data: &DirEntryData) -> Result<()> {
+        let template_values = CounterSettings::default().number_items(action, data);
+        let mut apply = action.get_action_with(&[], &template_values, &WrittenFiles::default());
+
+        let outcomes = data
+            .iter()
+            .map(|entry| (entry.path(), apply(entry, !self.execute)))
+            .collect::<Vec<_>>();
+
+        if !self.execute {
+            let mut failed_count = 0;
+            for (path, outcome) in &outcomes {
+                match outcome {
+                    Ok(ActionResultKind::Preview { msg, .. }) => println!("{msg}"),
+                    Ok(_) => (),
+                    Err(err) => {
+                        eprintln!("{}: {err}", path.display());
+                        failed_count += 1;
+                    }
+                }
+            }
+            if failed_count > 0 {
+                bail!("{failed_count} action(s) failed to preview, see the errors above.");
+            }
+            return Ok(());
+        }
+
+        let report = Reporting::with_outcomes(outcomes);
+        report.print_report();
+
+        let failed_count = report.failed_count();
+        if failed_count > 0 {
+            bail!("{failed_count} action(s) failed to apply, see the report above.");
+        }
+
+        Ok(())
+    }
+
+    /// Prints the action as it is pasted into a rule, in `preview` mode
+    fn print_config(&self) -> Result<()> {
+        let snippet = to_config_string(
+            &ActionSnippet {
+                actions: vec![ActionContainer {
+                    mode: ActionApplicationKind::Preview,
+                    action: self.action.clone(),
+                }],
+            },
+            self.format,
+        )?;
+
+        println!("This is an 'action' snippet for a {} config:", self.format);
         println!("'''");
-        println!("{yaml_string}");
+        println!("{snippet}");
         println!("'''");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{read_to_string, write},
+        io::Cursor,
+        path::Path,
+    };
+
+    use clap::Parser;
+    use rstest::rstest;
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        action: ActionCmd,
+    }
+
+    fn action_cmd(args: &[&str]) -> ActionCmd {
+        Cli::try_parse_from(std::iter::once("organize").chain(args.iter().copied()))
+            .unwrap()
+            .action
+    }
+
+    /// Two invoices to move and an existing, non-empty invoice in `dst`
+    fn setup_invoices() -> TempDir {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("dst")).unwrap();
+        write(dir.path().join("invoice_1.pdf"), "1").unwrap();
+        write(dir.path().join("invoice_2.pdf"), "2").unwrap();
+        write(dir.path().join("dst").join("invoice_2.pdf"), "existing").unwrap();
+        dir
+    }
+
+    fn path_arg(dir: &TempDir, path: impl AsRef<Path>) -> String {
+        dir.path().join(path).to_string_lossy().to_string()
+    }
+
+    #[rstest]
+    #[case::preview(false)]
+    #[case::execute(true)]
+    fn test_move_paths_passes(#[case] execute: bool) {
+        let dir = setup_invoices();
+        let (dst, invoice_1, invoice_2) = (
+            path_arg(&dir, "dst/"),
+            path_arg(&dir, "invoice_1.pdf"),
+            path_arg(&dir, "invoice_2.pdf"),
+        );
+        let mut args = vec![
+            "move",
+            "--dst",
+            &dst,
+            "--on-conflict",
+            "rename-new",
+            "--paths",
+            &invoice_1,
+            &invoice_2,
+        ];
+        if execute {
+            args.push("--execute");
+        }
+
+        action_cmd(&args).inner_run().unwrap();
+
+        let dst = dir.path().join("dst");
+        assert_eq!(dir.path().join("invoice_1.pdf").exists(), !execute);
+        assert_eq!(dir.path().join("invoice_2.pdf").exists(), !execute);
+        assert_eq!(dst.join("invoice_1.pdf").exists(), execute);
+        assert_eq!(
+            read_to_string(dst.join("invoice_2.pdf")).unwrap(),
+            "existing"
+        );
+        if execute {
+            assert_eq!(read_to_string(dst.join("invoice_2_1.pdf")).unwrap(), "2");
+        } else {
+            assert!(!dst.join("invoice_2_1.pdf").exists());
+        }
+    }
+
+    #[rstest]
+    #[case::preview(false)]
+    #[case::execute(true)]
+    fn test_move_with_unresolved_conflict_fails(#[case] execute: bool) {
+        let dir = setup_invoices();
+        let (dst, invoice_2) = (path_arg(&dir, "dst/"), path_arg(&dir, "invoice_2.pdf"));
+        let mut args = vec![
+            "move",
+            "--dst",
+            &dst,
+            "--on-conflict",
+            "overwrite-empty",
+            "--paths",
+            &invoice_2,
+        ];
+        if execute {
+            args.push("--execute");
+        }
+
+        let error = action_cmd(&args).inner_run().unwrap_err();
+
+        assert!(error.to_string().contains("1 conflict(s)"), "{error}");
+        assert!(dir.path().join("invoice_2.pdf").exists());
+        assert_eq!(
+            read_to_string(dir.path().join("dst").join("invoice_2.pdf")).unwrap(),
+            "existing"
+        );
+    }
+
+    #[test]
+    fn test_copy_into_file_fails() {
+        let dir = setup_invoices();
+        let (dst, invoice_1) = (
+            path_arg(&dir, "invoice_2.pdf/copies/"),
+            path_arg(&dir, "invoice_1.pdf"),
+        );
+
+        let error = action_cmd(&[
+            "copy",
+            "--dst",
+            &dst,
+            "--on-conflict",
+            "skip",
+            "--paths",
+            &invoice_1,
+            "--execute",
+        ])
+        .inner_run()
+        .unwrap_err();
+
+        assert!(error.to_string().contains("1 action(s) failed"), "{error}");
+    }
+
+    #[test]
+    fn test_read_paths_from_stdin_passes() {
+        let paths = ActionCmd::read_paths(Cursor::new(" a.txt \n\nb/c.txt\n")).unwrap();
+
+        assert_eq!(paths, [PathBuf::from("a.txt"), PathBuf::from("b/c.txt")]);
+    }
+
+    #[test]
+    fn test_stdin_conflicts_with_paths_fails() {
+        let result = Cli::try_parse_from(["organize", "trash", "--stdin", "--paths", "a.txt"]);
+
+        assert!(result.is_err());
     }
 }
@@ -2,14 +2,20 @@
 
 use std::path::PathBuf;
 
-use abscissa_core::{Command, Runnable};
+use abscissa_core::{status_err, Application, Command, Runnable, Shutdown};
 
+use anyhow::{bail, Result};
 use clap::{Args, Parser, Subcommand};
-use organize_rs_core::config::OrganizeConfig;
+use organize_rs_core::{
+    config::OrganizeConfig,
+    validation::{ConfigValidator, Diagnostic, SeverityKind},
+};
+
+use crate::application::ORGANIZE_APP;
 
 #[derive(Command, Debug, Args, Clone)]
 pub struct CheckConfigCmd {
-    /// Path the *.ron config to be checked
+    /// Path to the config to be checked
     #[clap(short, long)]
     path: PathBuf,
 }
@@ -23,7 +29,7 @@ pub struct CheckScriptCmd {
 
 #[derive(Subcommand, Command, Debug, Runnable)]
 pub enum CheckSubCmd {
-    /// Check given *.ron config file for errors
+    /// Check given config file for errors and questionable settings
     Config(CheckConfigCmd),
     /// Check given *.rhai script file for errors
     Script(CheckScriptCmd),
@@ -43,10 +49,50 @@ impl Runnable for CheckCmd {
     }
 }
 
+impl CheckConfigCmd {
+    fn inner_run(&self) -> Result<()> {
+        let config = OrganizeConfig::load_from_file(&self.path)?;
+        let diagnostics = ConfigValidator::new(&config).validate();
+
+        for diagnostic in &diagnostics {
+            println!("{diagnostic}");
+        }
+
+        let error_count = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .count();
+        if error_count > 0 {
+            bail!(
+                "{error_count} error(s) found in {}, see the diagnostics above.",
+                self.path.display()
+            );
+        }
+
+        println!(
+            "{} is valid ({} warning(s), {} info(s)).",
+            self.path.display(),
+            count_of(&diagnostics, SeverityKind::Warning),
+            count_of(&diagnostics, SeverityKind::Info),
+        );
+
+        Ok(())
+    }
+}
+
+fn count_of(diagnostics: &[Diagnostic], severity: SeverityKind) -> usize {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == severity)
+        .count()
+}
+
 impl Runnable for CheckConfigCmd {
     fn run(&self) {
-        let config = OrganizeConfig::load_from_file(&self.path);
-        println!("{config}");
+        if let Err(err) = self.inner_run() {
+            status_err!("{}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
     }
 }
 
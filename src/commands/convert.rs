@@ -0,0 +1,72 @@
+//! `convert` subcommand
+
+use std::path::PathBuf;
+
+use abscissa_core::{status_err, Application, Command, Runnable, Shutdown};
+
+use anyhow::Result;
+use clap::Parser;
+use organize_rs_core::{
+    config::{ConfigFileFormat, OrganizeConfig},
+    py_config::PyOrganizeConfig,
+};
+
+use crate::application::ORGANIZE_APP;
+
+/// `convert` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct ConvertCmd {
+    /// Path to a config of the Python version of organize
+    #[clap(long, value_name = "PATH")]
+    from_py: PathBuf,
+
+    /// Path to write the converted config to, its format is chosen by the
+    /// extension. Prints the config as YAML if not given.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Overwrite the output file if it exists
+    #[clap(long)]
+    overwrite: bool,
+}
+
+impl ConvertCmd {
+    fn inner_run(&self) -> Result<()> {
+        let conversion = PyOrganizeConfig::load_from_file(&self.from_py)?.convert();
+
+        for issue in &conversion.issues {
+            eprintln!("not converted: {issue}");
+        }
+
+        self.write(&conversion.config)?;
+
+        if let Some(output) = &self.output {
+            println!(
+                "Converted {} to {} ({} issue(s)). All actions are in preview mode, check the config before running it destructively.",
+                self.from_py.display(),
+                output.display(),
+                conversion.issues.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, config: &OrganizeConfig) -> Result<()> {
+        match &self.output {
+            Some(output) => config.write_to_file(output, self.overwrite)?,
+            None => print!("{}", config.to_string_as(ConfigFileFormat::Yaml)?),
+        }
+
+        Ok(())
+    }
+}
+
+impl Runnable for ConvertCmd {
+    fn run(&self) {
+        if let Err(err) = self.inner_run() {
+            status_err!("{}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
+    }
+}
@@ -1,18 +1,29 @@
 //! Filters that organize can apply
 
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use abscissa_core::{Command, Runnable};
-use clap::Args;
+use abscissa_core::{status_err, Application, Command, Runnable, Shutdown};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
 
 use organize_rs_core::{
+    actors::{filter_applicator::FilterApplicator, location_walker::LocationWalker},
+    config::{to_config_string, ConfigFileFormat},
+    filesystem::expand_path,
     filters::{
         FilterApplicationKind, FilterGroup, FilterGroupCollection, FilterGroupOperationKind,
         FilterKind, RecursiveFilterArgs,
     },
-    locations::TargetKind,
+    locations::{LocationCollection, LocationKind, MaxDepth, TargetKind},
 };
 
+use crate::application::ORGANIZE_APP;
+
 /// `filter` subcommand
 #[derive(Command, Debug, Args, Clone)]
 pub struct FilterCmd {
@@ -31,6 +42,18 @@ pub struct FilterCmd {
     #[arg(short, long, global = true, default_value_t = FilterApplicationKind::Any, value_enum)]
     filter_mode: FilterApplicationKind,
 
+    /// How the matching entries are listed
+    #[arg(long, global = true, default_value_t = ListFormatKind::Plain, value_enum)]
+    list_format: ListFormatKind,
+
+    /// Print the filter as a config snippet instead of applying it
+    #[arg(long, global = true)]
+    print_config: bool,
+
+    /// Format of the config snippet
+    #[arg(long, global = true, default_value_t = ConfigFileFormat::Yaml, value_enum)]
+    format: ConfigFileFormat,
+
     #[command(flatten)]
     location_opts: LocationOpts,
 }
@@ -50,8 +73,126 @@ pub struct LocationOpts {
     targets: TargetKind,
 }
 
+impl LocationOpts {
+    /// The locations to walk, without `--recursive` only the entries
+    /// directly within a location are considered
+    fn locations(&self) -> Result<LocationCollection> {
+        let max_depth = if self.recursive.recursive() {
+            self.recursive.max_depth()
+        } else {
+            1
+        };
+
+        let locations = self
+            .locations
+            .iter()
+            .map(|path| {
+                Ok(LocationKind::RecursiveWithMaxDepth {
+                    path: expand_path(path)?,
+                    max_depth: MaxDepth::new(max_depth),
+                    target: self.targets,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LocationCollection::from_vec(locations))
+    }
+}
+
+/// The filter groups, as they are pasted into a rule
+#[derive(Debug, Serialize)]
+struct FilterSnippet<'a> {
+    filter_groups: &'a FilterGroupCollection,
+}
+
+/// How `organize filter` lists the matching entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListFormatKind {
+    /// One path per line
+    #[default]
+    Plain,
+    /// Size, last modification time and path per line
+    Long,
+    /// A JSON array with path, size and last modification time
+    Json,
+}
+
+/// A matching entry, as it is listed
+#[derive(Debug, Serialize)]
+struct ListedEntry {
+    path: PathBuf,
+    size: Option<u64>,
+    last_modified: Option<DateTime<Local>>,
+}
+
+impl ListedEntry {
+    fn new(path: PathBuf) -> Self {
+        let metadata = path.metadata().ok();
+
+        Self {
+            size: metadata.as_ref().map(std::fs::Metadata::len),
+            last_modified: metadata
+                .and_then(|metadata| metadata.modified().ok())
+                .map(DateTime::<Local>::from),
+            path,
+        }
+    }
+
+    fn long_line(&self) -> String {
+        let size = self
+            .size
+            .map_or_else(|| "-".to_string(), |size| size.to_string());
+        let last_modified = self.last_modified.map_or_else(
+            || "-".to_string(),
+            |time| time.format("%Y-%m-%d %H:%M").to_string(),
+        );
+
+        format!("{size:>12} {last_modified:>16} {}", self.path.display())
+    }
+}
+
 impl Runnable for FilterCmd {
     fn run(&self) {
+        if let Err(err) = self.inner_run() {
+            status_err!("{}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
+    }
+}
+
+impl FilterCmd {
+    fn inner_run(&self) -> Result<()> {
+        let filters = self.filter_group_collection();
+
+        if self.print_config {
+            return self.print_config(&filters);
+        }
+
+        if self.location_opts.locations.is_empty() {
+            bail!("no locations given, pass them with `--locations` or use `--print-config`");
+        }
+
+        self.filter.check()?;
+        let paths = self.matching_paths(filters)?;
+
+        self.list(paths, &mut std::io::stdout().lock())
+    }
+
+    /// The sorted paths of the entries within the locations `filters` select
+    fn matching_paths(&self, filters: FilterGroupCollection) -> Result<Vec<PathBuf>> {
+        let data = LocationWalker::new(self.location_opts.locations()?).collect_dir_entry_data();
+        let mut paths = FilterApplicator::new(filters)
+            .get_applicable_items(data)
+            .into_iter()
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+
+        Ok(paths)
+    }
+
+    fn filter_group_collection(&self) -> FilterGroupCollection {
         let filter_group0 = FilterGroup::new(
             FilterGroupOperationKind::Include,
             self.filter_mode,
@@ -66,7 +207,7 @@ impl Runnable for FilterCmd {
                     in_name: ignore_names,
                 });
             }
-        };
+        }
 
         if let Some(ignore_paths) = self.ignore_path.clone() {
             if !ignore_paths.is_empty() {
@@ -74,32 +215,243 @@ impl Runnable for FilterCmd {
                     in_path: ignore_paths,
                 });
             }
-        };
+        }
 
         let mut filter_group = vec![filter_group0];
 
-        if !filters.is_empty() {
-            filter_group.push(FilterGroup::new(
-                FilterGroupOperationKind::Include,
+        // the ignore filters match items that don't contain the words, so an
+        // item is excluded if it doesn't match one of them
+        filter_group.extend(filters.into_iter().map(|filter| {
+            FilterGroup::new(
+                FilterGroupOperationKind::Exclude,
                 FilterApplicationKind::None,
-                filters,
-            ));
-        };
+                vec![filter],
+            )
+        }));
+
+        FilterGroupCollection::from_vec(filter_group)
+    }
 
-        let filter_group_collection = FilterGroupCollection::from_vec(filter_group);
+    fn list(&self, paths: Vec<PathBuf>, buf: &mut dyn Write) -> Result<()> {
+        match self.list_format {
+            ListFormatKind::Plain => {
+                for path in paths.iter().map(PathBuf::as_path).map(Path::display) {
+                    writeln!(buf, "{path}")?;
+                }
+            }
+            ListFormatKind::Long => {
+                for entry in paths.into_iter().map(ListedEntry::new) {
+                    writeln!(buf, "{}", entry.long_line())?;
+                }
+            }
+            ListFormatKind::Json => {
+                let entries = paths.into_iter().map(ListedEntry::new).collect::<Vec<_>>();
+                writeln!(buf, "{}", serde_json::to_string_pretty(&entries)?)?;
+            }
+        }
 
-        Self::inner_run(&filter_group_collection);
+        Ok(())
     }
-}
 
-impl FilterCmd {
-    fn inner_run(filters: &FilterGroupCollection) {
-        // TODO: Support different config file formats
-        // ? implement `FilterKind::to_config_string(format: ConfigFileFormat)`
-        let yaml_string = serde_yaml::to_string(&filters).unwrap();
-        println!("This is a 'filter' snippet for a yaml config:");
+    fn print_config(&self, filters: &FilterGroupCollection) -> Result<()> {
+        let snippet = to_config_string(
+            &FilterSnippet {
+                filter_groups: filters,
+            },
+            self.format,
+        )?;
+
+        println!("This is a 'filter' snippet for a {} config:", self.format);
         println!("'''");
-        println!("{yaml_string}");
+        println!("{snippet}");
         println!("'''");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use clap::Parser;
+    use filetime::FileTime;
+    use rstest::rstest;
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        filter: FilterCmd,
+    }
+
+    fn filter_cmd(args: &[&str]) -> FilterCmd {
+        Cli::try_parse_from(std::iter::once("organize").chain(args.iter().copied()))
+            .unwrap()
+            .filter
+    }
+
+    /// Two text files with a fixed modification time and a file in a
+    /// `target` folder
+    fn setup_documents() -> TempDir {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        for (file, content) in [
+            ("notes.txt", "notes"),
+            ("draft_notes.txt", "draft"),
+            ("target/build.txt", "build"),
+            ("image.jpg", "image"),
+        ] {
+            let path = dir.path().join(file);
+            write(&path, content).unwrap();
+            filetime::set_file_mtime(&path, FileTime::from_unix_time(1_686_830_400, 0)).unwrap();
+        }
+        dir
+    }
+
+    fn list_matches(dir: &TempDir, args: &[&str]) -> String {
+        let location = dir.path().to_string_lossy();
+        let mut args = args.to_vec();
+        args.extend(["--locations", &location, "extension", "--exts", "txt"]);
+        let cmd = filter_cmd(&args);
+        let paths = cmd.matching_paths(cmd.filter_group_collection()).unwrap();
+
+        let mut buf = vec![];
+        cmd.list(paths, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_list_plain_passes() {
+        let dir = setup_documents();
+
+        let listed = list_matches(&dir, &[]);
+
+        assert_eq!(
+            listed,
+            format!(
+                "{}\n{}\n",
+                dir.path().join("draft_notes.txt").display(),
+                dir.path().join("notes.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_list_long_passes() {
+        let dir = setup_documents();
+        let last_modified = DateTime::<Local>::from(
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_686_830_400),
+        )
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+
+        let listed = list_matches(&dir, &["--list-format", "long"]);
+
+        assert_eq!(
+            listed,
+            format!(
+                "{:>12} {last_modified:>16} {}\n{:>12} {last_modified:>16} {}\n",
+                5,
+                dir.path().join("draft_notes.txt").display(),
+                5,
+                dir.path().join("notes.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_list_json_passes() {
+        let dir = setup_documents();
+
+        let listed = list_matches(&dir, &["--list-format", "json"]);
+
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&listed).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1]["path"],
+            dir.path().join("notes.txt").to_string_lossy().as_ref()
+        );
+        assert_eq!(entries[1]["size"], 5);
+        let last_modified: DateTime<Local> =
+            serde_json::from_value(entries[1]["last_modified"].clone()).unwrap();
+        assert_eq!(last_modified.timestamp(), 1_686_830_400);
+    }
+
+    #[rstest]
+    #[case::ignore_name(&["--ignore-name", "draft"], &["notes.txt"])]
+    #[case::ignore_path(&["-r", "-m", "2", "--ignore-path", "target"], &["draft_notes.txt", "notes.txt"])]
+    #[case::recursive(&["-r", "-m", "2"], &["draft_notes.txt", "notes.txt", "target/build.txt"])]
+    fn test_list_with_ignored_entries_passes(#[case] args: &[&str], #[case] expected: &[&str]) {
+        let dir = setup_documents();
+
+        let listed = list_matches(&dir, args);
+
+        let expected = expected
+            .iter()
+            .map(|file| format!("{}\n", dir.path().join(file).display()))
+            .collect::<String>();
+        assert_eq!(listed, expected);
+    }
+
+    #[test]
+    fn test_invalid_regex_fails() {
+        let dir = setup_documents();
+        let location = dir.path().to_string_lossy();
+
+        let error = filter_cmd(&["--locations", &location, "regex", "--expr", "notes["])
+            .inner_run()
+            .unwrap_err();
+
+        assert!(
+            error.to_string().contains("invalid regular expression"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_ignore_filters_become_exclude_groups_passes() {
+        let cmd = filter_cmd(&[
+            "--ignore-name",
+            "draft",
+            "--ignore-path",
+            "target",
+            "--filter-mode",
+            "all",
+            "extension",
+            "--exts",
+            "txt",
+        ]);
+
+        let groups = cmd.filter_group_collection();
+
+        let groups = groups
+            .iter()
+            .map(|group| (group.operation, group.mode, group.filters.clone()))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            groups.as_slice(),
+            [
+                (
+                    FilterGroupOperationKind::Include,
+                    FilterApplicationKind::All,
+                    include,
+                ),
+                (
+                    FilterGroupOperationKind::Exclude,
+                    FilterApplicationKind::None,
+                    ignore_name,
+                ),
+                (
+                    FilterGroupOperationKind::Exclude,
+                    FilterApplicationKind::None,
+                    ignore_path,
+                ),
+            ] if matches!(include.as_slice(), [FilterKind::Extension { exts }] if exts == &["txt"])
+                && matches!(ignore_name.as_slice(), [FilterKind::IgnoreName { in_name }] if in_name == &["draft"])
+                && matches!(ignore_path.as_slice(), [FilterKind::IgnorePath { in_path }] if in_path == &["target"])
+        ));
     }
 }
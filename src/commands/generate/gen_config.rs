@@ -5,7 +5,7 @@ use anyhow::{bail, Result};
 use clap::{Args, Parser};
 use dialoguer::Confirm;
 use organize_rs_core::{
-    config::{OrganizeConfig, CONFIG_TEMPLATE_YAML},
+    config::{ConfigFileFormat, OrganizeConfig, CONFIG_TEMPLATE_YAML},
     rules::{empty_file_rule, empty_folder_rule, pdf_on_desktop_rule, Rules},
 };
 
@@ -22,9 +22,11 @@ pub struct GenConfigCmd {
 
     #[clap(flatten)]
     config_opts: GenConfigOpts,
-    // TODO: maybe explicitly chose?
-    // #[clap(long, default_value_t = ConfigFileFormat::Ron)]
-    // format: ConfigFileFormat,
+
+    /// format of the config file, derived from the extension of the
+    /// output path if not given
+    #[clap(long, value_enum, conflicts_with = "template")]
+    format: Option<ConfigFileFormat>,
 }
 
 #[derive(Debug, Args, Clone)]
@@ -73,17 +75,26 @@ impl GenConfigCmd {
 
         if File::open(&self.output_path).is_ok() {
             if Confirm::new().with_prompt("Config file already exists. We will overwrite it, do you have a backup and want to continue?").default(false).interact()? {
-                config.write_to_file(&self.output_path, true)?;
+                self.write_config(&config)?;
             } else {
                 bail!("Config file already exists. We will overwrite it, make sure you have a backup and agree in the dialog.");
             }
         } else {
-            config.write_to_file(&self.output_path, true)?;
+            self.write_config(&config)?;
         };
 
         Ok(())
     }
 
+    fn write_config(&self, config: &OrganizeConfig) -> Result<()> {
+        match self.format {
+            Some(format) => config.write_to_file_as(&self.output_path, format, true)?,
+            None => config.write_to_file(&self.output_path, true)?,
+        }
+
+        Ok(())
+    }
+
     fn generate_config_template_yaml(&self) -> Result<()> {
         // TODO: Handle in a better way
         let path = format!("{}{}", self.output_path.as_path().display(), ".tmpl.yaml");
@@ -0,0 +1,66 @@
+//! `migrate` subcommand
+
+use std::path::PathBuf;
+
+use abscissa_core::{status_err, Application, Command, Runnable, Shutdown};
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use organize_rs_core::config::{migrations::ConfigMigrator, CURRENT_CONFIG_VERSION};
+
+use crate::application::ORGANIZE_APP;
+
+#[derive(Command, Debug, Args, Clone)]
+pub struct MigrateConfigCmd {
+    /// Path to the config to be migrated
+    #[clap(short, long)]
+    path: PathBuf,
+}
+
+#[derive(Subcommand, Command, Debug, Runnable)]
+pub enum MigrateSubCmd {
+    /// Upgrade the given config file to the current config version in place
+    Config(MigrateConfigCmd),
+}
+
+/// `migrate` subcommand
+#[derive(Command, Debug, Parser)]
+pub struct MigrateCmd {
+    #[clap(subcommand)]
+    commands: MigrateSubCmd,
+}
+
+impl Runnable for MigrateCmd {
+    /// Start the application.
+    fn run(&self) {
+        self.commands.run();
+    }
+}
+
+impl MigrateConfigCmd {
+    fn inner_run(&self) -> Result<()> {
+        match ConfigMigrator::default().migrate_file(&self.path)? {
+            Some(migrated) => println!(
+                "Migrated {} from version {} to {CURRENT_CONFIG_VERSION}, the original config has been saved to {}.",
+                self.path.display(),
+                migrated.from,
+                migrated.backup.display()
+            ),
+            None => println!(
+                "{} is up to date (version {CURRENT_CONFIG_VERSION}).",
+                self.path.display()
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+impl Runnable for MigrateConfigCmd {
+    fn run(&self) {
+        if let Err(err) = self.inner_run() {
+            status_err!("{}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
+    }
+}
@@ -30,6 +30,9 @@ pub struct RunCmd {
 
 impl Runnable for RunCmd {
     fn run(&self) {
-        self.commands.run();
+        match &self.commands {
+            RunSubCmd::Script(cmd) => cmd.run_with(self.execute),
+            RunSubCmd::Config(_) => self.commands.run(),
+        }
     }
 }
@@ -4,7 +4,7 @@ use std::path::PathBuf;
 
 use abscissa_core::{status_err, Application, Command, Runnable};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Args;
 use dialoguer::Confirm;
 use organize_rs_core::{runner::Runner, state::Initialize, tags::Tag};
@@ -25,33 +25,33 @@ pub struct RunConfigCmd {
 
 impl RunConfigCmd {
     fn inner_run(&self) -> Result<()> {
-        let runner = Runner::<Initialize>::load_configs(&self.paths)
+        let runner = Runner::<Initialize>::load_configs(&self.paths)?
             .apply_filters(self.tags.clone())
             .inspect_entries()
             .finish_inspection()
-            .preview_actions()?;
+            .preview_actions()?
+            .check_conflicts();
+
+        let runner = if runner.conflicts().is_empty() {
+            runner.finish_conflict_handling()
+        } else {
+            // preview again, so the resolved conflicts are part of the preview
+            runner.view_conflicts()?.preview_actions()?
+        };
 
         if Confirm::new()
         .with_prompt("Are you sure, that you want to execute the previewed actions? This is irreversible.")
         .default(false)
         .interact()? {
 
-            runner.apply_actions()?;
-            // ? Conflict handling
-            // * Probably done in a loop until all the conflicts are handled
-            // * loop can be interrupted
-            // * should jump to report of actions
-            // loop {
-            //      runner
-            //      .check_conflicts()
-            //      .view_conflicts()
-            //      .preview_actions()
-            //      .ask_confirmation()?
-            //      .apply_actions()?
-            // }
+            let report = runner.apply_actions()?.print_report();
+
+            let failed_count = report.report().failed_count();
+            if failed_count > 0 {
+                bail!("{failed_count} action(s) failed to apply, see the report above.");
+            }
         }
 
-        // runner.print_report();
         Ok(())
     }
 }
@@ -3,13 +3,10 @@
 use std::path::PathBuf;
 
 use abscissa_core::{status_err, Application, Command, Runnable, Shutdown};
-use anyhow::Result;
 use clap::Parser;
 
-use rhai::{Engine, EvalAltResult};
-
 use crate::application::ORGANIZE_APP;
-use crate::scripting::add;
+use crate::scripting::run_script;
 
 /// Run a *.rhai script with organize
 #[derive(Command, Debug, Parser)]
@@ -19,29 +16,18 @@ pub struct RunScriptCmd {
     path: PathBuf,
 }
 
-impl Runnable for RunScriptCmd {
-    fn run(&self) {
-        todo!("Scripting is not implemented (yet)");
-        match start_scripting_engine(&self.path) {
-            Ok(_) => {}
-            Err(err) => {
-                status_err!("failed to execute script: {}", err);
-                ORGANIZE_APP.shutdown(Shutdown::Crash)
-            }
-        };
+impl RunScriptCmd {
+    /// Runs the script, its actions are only previewed unless `execute` is `true`
+    pub fn run_with(&self, execute: bool) {
+        if let Err(err) = run_script(&self.path, execute) {
+            status_err!("failed to execute script: {}", err);
+            ORGANIZE_APP.shutdown(Shutdown::Crash);
+        }
     }
 }
 
-fn start_scripting_engine(path: impl Into<PathBuf>) -> Result<(), Box<EvalAltResult>> {
-    // Create an 'Engine'
-    let mut engine = Engine::new();
-
-    engine.register_fn("add", add);
-
-    // engine.build_type::<FilterCmd>();
-
-    engine.run_file(path.into())?;
-
-    // Done!
-    Ok(())
+impl Runnable for RunScriptCmd {
+    fn run(&self) {
+        self.run_with(false);
+    }
 }
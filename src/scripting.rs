@@ -1,16 +1,334 @@
-// use rhai::{CustomType, TypeBuilder};
-
-// impl CustomType for FilterCmd {
-//     fn build(mut builder: TypeBuilder<'_, Self>) {
-//         #[allow(deprecated)] // The TypeBuilder api is volatile.
-//         builder
-//             .with_name("FilterCmd")
-//             .with_fn("run_filter", Self::run_filter);
-//         // .is_iterable()
-//         // .with_get_set("x", Self::get_x, Self::set_x);
-//     }
-// }
-
-pub fn add(x: i64, y: i64) -> i64 {
-    x + y
+//! Scripting API to run organize from `*.rhai` scripts
+//!
+//! ```rhai
+//! let entries = init_organize("~/Downloads");
+//! let apps = filter::by_extension(entries, ["exe", "msi", "apk"]);
+//! let recent = filter::by_last_modified(apps, "..7d");
+//!
+//! action::move(recent, "~/backup/INBOX/@Apps/");
+//! ```
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use rhai::{Array, CustomType, Engine, EvalAltResult, Module, TypeBuilder};
+
+use organize_rs_core::{
+    actions::{conflicts::ConflictResolutionKind, ActionKind, ActionResultKind, WrittenFiles},
+    actors::location_walker::{DirEntryData, LocationWalker},
+    counters::CounterSettings,
+    filesystem::expand_path,
+    filters::FilterKind,
+    locations::{LocationCollection, LocationKind, MaxDepth, TargetKind},
+    parsers::period_range::PeriodRange,
+};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Entries of a location, that can be passed from filter to filter
+/// and finally to an action
+#[derive(Debug, Clone, Default)]
+pub struct Entries {
+    paths: Vec<PathBuf>,
+}
+
+impl Entries {
+    fn len(&mut self) -> i64 {
+        i64::try_from(self.paths.len()).unwrap_or(i64::MAX)
+    }
+
+    /// The entries as they are walked, entries that vanished in the
+    /// meantime are left out
+    fn dir_entries(&self) -> impl Iterator<Item = jwalk::DirEntry<((), ())>> + '_ {
+        self.paths
+            .iter()
+            .filter_map(|path| jwalk::WalkDir::new(path).into_iter().next()?.ok())
+    }
+
+    /// Keeps the entries `filter` matches
+    fn filtered_by(&self, filter: &FilterKind) -> Self {
+        let mut filter = filter.get_filter();
+
+        Self {
+            paths: self
+                .dir_entries()
+                .filter(|entry| filter(entry))
+                .map(|entry| entry.path())
+                .collect(),
+        }
+    }
+}
+
+impl Display for Entries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Entries ({}):", self.paths.len())?;
+        for path in &self.paths {
+            writeln!(f, "  {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl CustomType for Entries {
+    fn build(mut builder: TypeBuilder<'_, Self>) {
+        #[allow(deprecated)] // The TypeBuilder api is volatile.
+        builder
+            .with_name("Entries")
+            .with_get("len", Self::len)
+            .with_fn("to_string", |entries: &mut Self| entries.to_string())
+            .with_fn("to_debug", |entries: &mut Self| format!("{entries:?}"));
+    }
+}
+
+/// Creates a scripting engine with the organize API registered
+///
+/// Actions are only previewed, unless `execute` is `true`.
+pub fn scripting_engine(execute: bool) -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .build_type::<Entries>()
+        .register_fn("init_organize", init_organize)
+        .register_fn("init_organize", init_organize_with_max_depth)
+        .register_static_module("filter", filter_module().into())
+        .register_static_module("action", action_module(execute).into());
+
+    engine
+}
+
+/// Runs the script at `path`
+///
+/// # Errors
+///
+/// If the script can't be read, compiled or fails while running
+pub fn run_script(path: impl Into<PathBuf>, execute: bool) -> ScriptResult<()> {
+    scripting_engine(execute).run_file(path.into())
+}
+
+/// Collects the files directly within `folder`
+fn init_organize(folder: &str) -> ScriptResult<Entries> {
+    init_organize_with_max_depth(folder, 1)
+}
+
+/// Collects the files within `folder` up to `max_depth` levels deep
+fn init_organize_with_max_depth(folder: &str, max_depth: i64) -> ScriptResult<Entries> {
+    let path = expand_path(Path::new(folder)).map_err(|err| err.to_string())?;
+    let max_depth = u64::try_from(max_depth)
+        .map_err(|_| format!("max depth needs to be positive, got {max_depth}"))?;
+
+    let location = LocationKind::RecursiveWithMaxDepth {
+        path,
+        max_depth: MaxDepth::new(max_depth),
+        target: TargetKind::Files,
+    };
+    let data =
+        LocationWalker::new(LocationCollection::from_vec(vec![location])).collect_dir_entry_data();
+
+    Ok(Entries {
+        paths: data.into_iter().map(|entry| entry.path()).collect(),
+    })
+}
+
+/// The `filter::` functions, backed by [`FilterKind`]
+fn filter_module() -> Module {
+    let mut module = Module::new();
+
+    _ = module.set_native_fn("by_extension", by_extension);
+    _ = module.set_native_fn("by_last_modified", by_last_modified);
+
+    module
+}
+
+fn by_extension(entries: &mut Entries, exts: Array) -> ScriptResult<Entries> {
+    let exts = exts
+        .into_iter()
+        .map(|ext| {
+            ext.into_string()
+                .map_err(|type_name| format!("extensions need to be strings, got {type_name}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries.filtered_by(&FilterKind::Extension { exts }))
+}
+
+fn by_last_modified(entries: &mut Entries, range: &str) -> ScriptResult<Entries> {
+    let range = PeriodRange::from_str(range)
+        .map_err(|err| format!("invalid period range `{range}`: {err}"))?;
+
+    Ok(entries.filtered_by(&FilterKind::LastModified { range: Some(range) }))
+}
+
+/// The `action::` functions, backed by [`ActionKind`]
+fn action_module(execute: bool) -> Module {
+    let mut module = Module::new();
+
+    _ = module.set_native_fn("move", move |entries: &mut Entries, dst: &str| {
+        move_entries(entries, dst, execute)
+    });
+
+    module
+}
+
+/// Moves the entries to `dst` and returns the number of entries the
+/// action has been applied to without errors
+fn move_entries(entries: &Entries, dst: &str, execute: bool) -> ScriptResult<i64> {
+    let action = ActionKind::Move {
+        dst: expand_path(Path::new(dst)).map_err(|err| err.to_string())?,
+        on_conflict: ConflictResolutionKind::default(),
+        rename_template: None,
+        filesystem: None,
+    };
+
+    Ok(apply_action(&action, entries, execute))
+}
+
+/// Applies `action` to all entries, previews it unless `execute` is `true`
+fn apply_action(action: &ActionKind, entries: &Entries, execute: bool) -> i64 {
+    let data = DirEntryData::from(entries.dir_entries().collect::<Vec<_>>());
+    let template_values = CounterSettings::default().number_items(action, &data);
+    let mut apply = action.get_action_with(&[], &template_values, &WrittenFiles::default());
+    let mut applied = 0;
+
+    for entry in data {
+        match apply(&entry, !execute) {
+            Ok(ActionResultKind::Preview { msg, .. }) => {
+                println!("{msg}");
+                applied += 1;
+            }
+            Ok(ActionResultKind::Skipped) => {
+                println!("Skipped '{}'", entry.path().display());
+            }
+            Ok(_) => applied += 1,
+            Err(err) => eprintln!("{}: {err}", entry.path().display()),
+        }
+    }
+
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use filetime::FileTime;
+    use rstest::rstest;
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    /// A folder with two fresh apps, one directly in it and one nested,
+    /// an app, that hasn't been modified for 30 days, and a text file
+    fn setup_downloads() -> TempDir {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        for file in ["setup.exe", "old.msi", "notes.txt", "nested/game.apk"] {
+            std::fs::write(dir.path().join(file), file).unwrap();
+        }
+        let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+        filetime::set_file_mtime(
+            dir.path().join("old.msi"),
+            FileTime::from_system_time(month_ago),
+        )
+        .unwrap();
+        dir
+    }
+
+    fn move_apps_script(dir: &Path, init: &str) -> String {
+        format!(
+            r#"
+            let entries = {init};
+            let apps = filter::by_extension(entries, ["exe", "msi", "apk"]);
+            let recent = filter::by_last_modified(apps, "..7d");
+            action::move(recent, {dst:?})
+            "#,
+            dst = format!("{}/backup/", dir.display()),
+        )
+    }
+
+    #[rstest]
+    #[case::preview(
+        false,
+        "init_organize({path})",
+        1,
+        &[],
+        &["setup.exe", "old.msi", "notes.txt", "nested/game.apk"]
+    )]
+    #[case::execute(
+        true,
+        "init_organize({path})",
+        1,
+        &["setup.exe"],
+        &["old.msi", "notes.txt", "nested/game.apk"]
+    )]
+    #[case::execute_nested(
+        true,
+        "init_organize({path}, 2)",
+        2,
+        &["setup.exe", "game.apk"],
+        &["old.msi", "notes.txt"]
+    )]
+    fn test_script_moves_recent_apps_passes(
+        #[case] execute: bool,
+        #[case] init: &str,
+        #[case] expected: i64,
+        #[case] moved: &[&str],
+        #[case] kept: &[&str],
+    ) {
+        let dir = setup_downloads();
+        let init = init.replace("{path}", &format!("{:?}", dir.path().display().to_string()));
+
+        let applied = scripting_engine(execute)
+            .eval::<i64>(&move_apps_script(dir.path(), &init))
+            .unwrap();
+
+        assert_eq!(applied, expected);
+        for file in moved {
+            assert!(dir.path().join("backup").join(file).exists(), "{file}");
+        }
+        for file in kept {
+            assert!(dir.path().join(file).exists(), "{file}");
+        }
+        if !execute {
+            assert!(!dir.path().join("backup").exists());
+        }
+    }
+
+    #[test]
+    fn test_script_entries_passes() {
+        let dir = setup_downloads();
+        let script = format!(
+            "let entries = init_organize({:?}); [entries.len, filter::by_extension(entries, [\"txt\"]).to_string()]",
+            dir.path().display().to_string()
+        );
+
+        let result = scripting_engine(false).eval::<Array>(&script).unwrap();
+
+        assert_eq!(result[0].as_int().unwrap(), 3);
+        assert_eq!(
+            result[1].clone().into_string().unwrap(),
+            format!(
+                "Entries (1):\n  {}\n",
+                dir.path().join("notes.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_script_with_invalid_period_range_fails() {
+        let dir = setup_downloads();
+        let script = format!(
+            "filter::by_last_modified(init_organize({:?}), \"last week\")",
+            dir.path().display().to_string()
+        );
+
+        let error = scripting_engine(false).run(&script).unwrap_err();
+
+        assert!(
+            error.to_string().contains("invalid period range"),
+            "{error}"
+        );
+    }
 }